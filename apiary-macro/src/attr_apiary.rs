@@ -1,28 +1,86 @@
 use proc_macro2::TokenStream;
 use proc_macro_error::{emit_call_site_error, emit_error};
+use syn::parse::{Parse, ParseStream, Parser};
 use syn::parse_quote;
 use syn::punctuated::Punctuated;
 
+mod cors;
 mod extract;
 mod fixture;
+mod host;
 mod parse;
-
+mod routes;
 mod server;
+mod urls;
 
 use fixture::Fixture;
-use parse::{ParamSrc, Parsed};
 
-pub fn process(args: syn::AttributeArgs, mut input_trait: syn::ItemTrait) -> Option<TokenStream> {
+/// Parameter of the `#[api]`. `cors(...)` takes the values which aren't valid as the attribute meta.
+enum ApiArg {
+    Cors(syn::Ident, TokenStream),
+    Meta(syn::NestedMeta),
+}
+
+impl Parse for ApiArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let is_cors = input.peek2(syn::token::Paren)
+            && input
+                .fork()
+                .parse::<syn::Ident>()
+                .is_ok_and(|ident| ident == Fixture::CORS);
+
+        if is_cors {
+            let ident = input.parse()?;
+            let content;
+            syn::parenthesized!(content in input);
+            Ok(ApiArg::Cors(ident, content.parse()?))
+        } else {
+            Ok(ApiArg::Meta(input.parse()?))
+        }
+    }
+}
+
+pub fn process(args: TokenStream, mut input_trait: syn::ItemTrait) -> Option<TokenStream> {
     let fixture = Fixture::new();
     if async_trait_expanded_before(&input_trait, &fixture) {
         emit_call_site_error!("#[api] should be placed above the #[async_trait]");
         return None;
     }
 
+    let args = match Punctuated::<ApiArg, syn::Token![,]>::parse_terminated.parse2(args) {
+        Ok(args) => args,
+        Err(err) => {
+            emit_error!(err.span(), "{}", err);
+            return None;
+        }
+    };
+
     let mut server_args = None;
+    let mut cors_args = None;
+    let mut host_args = None;
+    let mut urls_args = None;
 
     for arg in args {
+        let arg = match arg {
+            ApiArg::Cors(ident, tokens) => {
+                if cors_args.is_some() {
+                    emit_error!(ident, "Duplicated cors parameter");
+                } else {
+                    cors_args = cors::parse_args(tokens);
+                }
+                continue;
+            }
+            ApiArg::Meta(arg) => arg,
+        };
         let (path, args) = match arg {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if fixture.is_host(&nv.path) => {
+                if host_args.is_some() {
+                    emit_error!(nv.path, "Duplicated host parameter");
+                } else {
+                    host_args = host::parse_args(&nv.lit);
+                }
+                continue;
+            }
             syn::NestedMeta::Lit(_) | syn::NestedMeta::Meta(syn::Meta::NameValue(_)) => {
                 emit_error!(arg, "Invalid parameter");
                 continue;
@@ -37,18 +95,63 @@ pub fn process(args: syn::AttributeArgs, mut input_trait: syn::ItemTrait) -> Opt
             } else {
                 server_args = server::parse_args(args);
             }
+        } else if fixture.is_urls(&path) {
+            if urls_args.is_some() {
+                emit_error!(path, "Duplicated urls parameter");
+            } else {
+                urls_args = urls::parse_args(args);
+            }
         } else {
             emit_error!(path, "Invalid parameter");
         }
     }
 
     let extracted = extract::extract(&mut input_trait, &fixture)?;
-    let parsed = parse::parse(&extracted, &fixture)?;
+    let parsed = parse::parse(&extracted, &fixture, host_args.as_ref())?;
+
+    input_trait.items.push(routes::codegen(&parsed));
+
+    if server_args.is_some() {
+        input_trait.items.push(parse_quote! {
+            /// Renders the request the generated server rejected before reaching the handler.
+            ///
+            /// Override it to customize the responses of the rejections.
+            fn render_rejection(
+                &self,
+                rejection: apiary::rejection::Rejection,
+            ) -> std::result::Result<
+                apiary::http::Response<apiary::response::Body>,
+                apiary::BoxError,
+            > {
+                apiary::response::Response::into_response(rejection)
+            }
+        });
+    }
 
     let mut generated = vec![syn::Item::Trait(input_trait)];
 
+    if let Some(args) = urls_args {
+        let path_versioning = server_args
+            .as_ref()
+            .is_some_and(server::Args::path_versioning);
+        generated.append(&mut urls::codegen(args, &parsed, path_versioning));
+    }
+
     if let Some(args) = server_args {
-        generated.append(&mut server::codegen(args, &parsed));
+        let cors = cors_args.as_ref().map(cors::codegen);
+        generated.append(&mut server::codegen(
+            args,
+            &parsed,
+            host_args.as_ref(),
+            cors,
+        ));
+    } else {
+        if cors_args.is_some() {
+            emit_call_site_error!("cors parameter requires the server parameter");
+        }
+        if host_args.is_some() {
+            emit_call_site_error!("host parameter requires the server parameter");
+        }
     }
 
     Some(quote::quote! {
@@ -67,110 +170,3 @@ fn async_trait_expanded_before(input_trait: &syn::ItemTrait, fixture: &Fixture)
         .flat_map(|meth| &meth.sig.generics.params)
         .any(|p| fixture.is_async_trait_param(p))
 }
-
-pub fn _codegen(parsed: &Parsed) -> syn::TraitItemMethod {
-    let body: Vec<syn::Stmt> = parsed
-        .handlers
-        .iter()
-        .map(|handler| {
-            let method = handler.http_method.ident();
-            let method: syn::Expr = parse_quote!(apiary::Method::#method);
-            let handler_name = handler.name.clone();
-            let path_len = handler.path.len();
-
-            let decl_fields: Vec<syn::Field> = handler
-                .params
-                .iter()
-                .map(|param| syn::Field {
-                    attrs: vec![],
-                    vis: syn::Visibility::Inherited,
-                    ident: Some(param.name.clone()),
-                    colon_token: Some(Default::default()),
-                    ty: param.ty.clone(),
-                })
-                .collect();
-
-            let mut conditions: Vec<syn::Expr> = vec![
-                parse_quote!(req.method() != #method),
-                parse_quote!(path.len() != #path_len),
-            ];
-            conditions.extend(
-                handler
-                    .path
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(idx, seg)| Some((idx, seg.as_deref()?)))
-                    .map(|(idx, seg)| parse_quote!(path[#idx] != #seg)),
-            );
-            let conditions = conditions.into_iter().rev().fold(
-                parse_quote!(true),
-                |acc: syn::Expr, new: syn::Expr| parse_quote!(#new && #acc),
-            );
-
-            let parse_fields: Vec<syn::Stmt> = handler
-                .params
-                .iter()
-                .map(|param| {
-                    let name = param.name.clone();
-                    let ty = param.ty.clone();
-
-                    match &param.src {
-                        ParamSrc::Path { idx } => parse_quote! {
-                            let #name: #ty = path[#idx].parse().ok()?;
-                        },
-                    }
-                })
-                .collect();
-
-            let field_names: Vec<syn::Ident> = handler
-                .params
-                .iter()
-                .map(|param| param.name.clone())
-                .collect();
-
-            parse_quote! {{
-                struct Params {
-                    #(#decl_fields,)*
-                }
-
-                fn parse(req: &apiary::Request<String>, path: &[&str]) -> Option<Params> {
-                    if #conditions {
-                        return None;
-                    }
-
-                    #(#parse_fields)*
-
-                    Some(Params {
-                        #(#field_names),*
-                    })
-                }
-
-                if let Some(Params { #(#field_names),* }) = parse(&req, &path) {
-                    return tokio::spawn(async move {
-                        let res = this.#handler_name( #(#field_names),* ).await;
-                        Ok(apiary::Response::new(res))
-                    })
-                }
-            }}
-        })
-        .collect();
-
-    parse_quote! {
-        fn router(self: Arc<Self>) -> apiary::Router<Self> {
-            apiary::Router {
-                app: self,
-                handler: |this, req, closed| -> apiary::router::HandlerResult {
-                    let path: Vec<_> = req
-                        .uri()
-                        .path()
-                        .strip_prefix("/")
-                        .expect("Path doesn't starts with `/`")
-                        .split('/')
-                        .collect();
-                    #(#body)*
-                    tokio::spawn(apiary::default_404_not_found(req))
-                },
-            }
-        }
-    }
-}
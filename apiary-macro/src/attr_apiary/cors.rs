@@ -0,0 +1,213 @@
+use http::header::HeaderName;
+use proc_macro2::TokenStream;
+use proc_macro_error::emit_error;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+
+const ORIGINS: &str = "origins";
+const METHODS: &str = "methods";
+const HEADERS: &str = "headers";
+const EXPOSE_HEADERS: &str = "expose_headers";
+const CREDENTIALS: &str = "credentials";
+const MAX_AGE: &str = "max_age";
+const ANY: &str = "*";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    any_origin: bool,
+    origins: Vec<String>,
+    methods: Vec<String>,
+    any_header: bool,
+    headers: Vec<String>,
+    expose_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<u64>,
+}
+
+/// `name` or `name = value`, where the value can be an array of literals.
+struct Arg {
+    name: syn::Ident,
+    value: Option<syn::Expr>,
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let value = if input.peek(syn::Token![=]) {
+            input.parse::<syn::Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Arg { name, value })
+    }
+}
+
+pub fn parse_args(tokens: TokenStream) -> Option<Args> {
+    let parsed = match Punctuated::<Arg, syn::Token![,]>::parse_terminated.parse2(tokens) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            emit_error!(err.span(), "Failed to parse cors parameters: {}", err);
+            return None;
+        }
+    };
+    let mut args = Args::default();
+    let mut credentials = None;
+
+    for arg in parsed {
+        let name = arg.name.to_string();
+
+        match (name.as_str(), &arg.value) {
+            (ORIGINS, Some(value)) => {
+                for origin in strings(value)? {
+                    if origin == ANY {
+                        args.any_origin = true;
+                    } else if is_origin(&origin) {
+                        args.origins.push(origin);
+                    } else {
+                        emit_error!(
+                            value,
+                            "Invalid origin {}, expected like `https://example.com`",
+                            origin
+                        );
+                    }
+                }
+            }
+            (METHODS, Some(value)) => {
+                for method in strings(value)? {
+                    if http::Method::from_bytes(method.as_bytes()).is_err() {
+                        emit_error!(value, "Invalid HTTP method {}", method);
+                    }
+                    args.methods.push(method);
+                }
+            }
+            (HEADERS, Some(value)) => {
+                for header in strings(value)? {
+                    if header == ANY {
+                        args.any_header = true;
+                    } else {
+                        args.headers.push(header_name(value, &header)?);
+                    }
+                }
+            }
+            (EXPOSE_HEADERS, Some(value)) => {
+                for header in strings(value)? {
+                    args.expose_headers.push(header_name(value, &header)?);
+                }
+            }
+            (CREDENTIALS, None) => args.credentials = true,
+            (
+                CREDENTIALS,
+                Some(syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Bool(lit),
+                    ..
+                })),
+            ) => args.credentials = lit.value,
+            (
+                MAX_AGE,
+                Some(syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(lit),
+                    ..
+                })),
+            ) => match lit.base10_parse() {
+                Ok(secs) => args.max_age = Some(secs),
+                Err(err) => emit_error!(lit, "Invalid max_age: {}", err),
+            },
+            _ => emit_error!(arg.name, "Invalid cors parameter"),
+        }
+        if name == CREDENTIALS {
+            credentials = Some(arg.name);
+        }
+    }
+
+    // any site could read the responses with the user's cookies
+    if let Some(credentials) = credentials.filter(|_| args.any_origin && args.credentials) {
+        emit_error!(
+            credentials,
+            "Credentials can't be allowed for any origin, list the origins instead"
+        );
+    }
+
+    Some(args)
+}
+
+/// Takes `"value"` or `["value", ...]`.
+fn strings(value: &syn::Expr) -> Option<Vec<String>> {
+    let lit_str = |expr: &syn::Expr| match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit),
+            ..
+        }) => Some(lit.value()),
+        _ => {
+            emit_error!(expr, "Expected a string literal");
+            None
+        }
+    };
+
+    match value {
+        syn::Expr::Array(array) => array.elems.iter().map(lit_str).collect(),
+        other => Some(vec![lit_str(other)?]),
+    }
+}
+
+fn is_origin(origin: &str) -> bool {
+    match origin.parse::<http::Uri>() {
+        Ok(uri) => {
+            uri.scheme().is_some()
+                && uri.authority().is_some()
+                && uri.path_and_query().is_none_or(|path| path.as_str() == "/")
+                && !origin.ends_with('/')
+        }
+        Err(_) => false,
+    }
+}
+
+fn header_name(span: &impl Spanned, name: &str) -> Option<String> {
+    match HeaderName::from_bytes(name.as_bytes()) {
+        Ok(name) => Some(name.as_str().to_owned()),
+        Err(_) => {
+            emit_error!(span.span(), "Invalid header name {}", name);
+            None
+        }
+    }
+}
+
+/// Expression which builds the `apiary::cors::CorsConfig`.
+pub fn codegen(args: &Args) -> TokenStream {
+    let mut config = quote::quote!(apiary::cors::CorsConfig::new());
+
+    if args.any_origin {
+        config = quote::quote!(#config.allow_any_origin());
+    }
+    for origin in &args.origins {
+        config = quote::quote!(#config.allow_origin(#origin));
+    }
+    for method in &args.methods {
+        config = quote::quote! {
+            #config.allow_method(apiary::http::Method::from_bytes(#method.as_bytes()).unwrap())
+        };
+    }
+    if args.any_header {
+        config = quote::quote!(#config.allow_any_header());
+    }
+    for header in &args.headers {
+        config = quote::quote! {
+            #config.allow_header(apiary::http::header::HeaderName::from_static(#header))
+        };
+    }
+    for header in &args.expose_headers {
+        config = quote::quote! {
+            #config.expose_header(apiary::http::header::HeaderName::from_static(#header))
+        };
+    }
+    if args.credentials {
+        config = quote::quote!(#config.allow_credentials(true));
+    }
+    if let Some(secs) = args.max_age {
+        config = quote::quote!(#config.max_age(std::time::Duration::from_secs(#secs)));
+    }
+
+    config
+}
@@ -6,6 +6,7 @@ use super::fixture::Fixture;
 
 #[derive(Debug)]
 pub struct Extracted {
+    pub vis: syn::Visibility,
     pub trait_name: syn::Ident,
     pub methods: Vec<Method>,
 }
@@ -13,6 +14,8 @@ pub struct Extracted {
 #[derive(Debug)]
 pub struct Method {
     pub attrs: Vec<syn::Attribute>,
+    /// Lines of the doc comment joined with the newline.
+    pub doc: String,
     pub name: syn::Ident,
     pub args: Vec<Arg>,
     pub return_ty: syn::Type,
@@ -36,6 +39,7 @@ pub fn extract(input_trait: &mut syn::ItemTrait, fixture: &Fixture) -> Option<Ex
         .collect();
 
     Some(Extracted {
+        vis: input_trait.vis.clone(),
         trait_name: input_trait.ident.clone(),
         methods,
     })
@@ -47,6 +51,7 @@ fn extract_method(method: &mut syn::TraitItemMethod, fixture: &Fixture) -> Optio
         .into_iter()
         .partition(|attr| fixture.is_method_attr(attr));
     method.attrs = remaining;
+    let doc = doc_string(&method.attrs, fixture);
 
     if attrs.is_empty() {
         // method not related with the HTTP route
@@ -55,7 +60,7 @@ fn extract_method(method: &mut syn::TraitItemMethod, fixture: &Fixture) -> Optio
 
     let sig = method.sig.clone();
     let mut args = method.sig.inputs.iter_mut();
-    let is_arc_self = args.next().map_or(false, |arg| fixture.is_arc_self(arg));
+    let is_arc_self = args.next().is_some_and(|arg| fixture.is_arc_self(arg));
     if !is_arc_self {
         emit_error!(
             sig,
@@ -96,6 +101,7 @@ fn extract_method(method: &mut syn::TraitItemMethod, fixture: &Fixture) -> Optio
 
     Some(Method {
         attrs,
+        doc,
         name: sig.ident,
         args,
         return_ty: match sig.output {
@@ -107,3 +113,20 @@ fn extract_method(method: &mut syn::TraitItemMethod, fixture: &Fixture) -> Optio
         },
     })
 }
+
+/// Collects the `#[doc = "..."]` attributes, which the `///` comments desugar into.
+fn doc_string(attrs: &[syn::Attribute], fixture: &Fixture) -> String {
+    attrs
+        .iter()
+        .filter(|attr| fixture.is_doc(&attr.path))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(lit),
+                ..
+            })) => Some(lit.value()),
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').map(str::to_owned).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
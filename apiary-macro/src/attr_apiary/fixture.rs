@@ -1,4 +1,7 @@
 use syn::parse_quote;
+
+use super::parse::Method;
+
 #[derive(Debug)]
 pub struct Fixture {
     lt_param_async_trait: syn::GenericParam,
@@ -8,8 +11,23 @@ pub struct Fixture {
 
 impl Fixture {
     const GET: &'static str = "get";
+    const POST: &'static str = "post";
+    const PUT: &'static str = "put";
+    const PATCH: &'static str = "patch";
+    const DELETE: &'static str = "delete";
+    const HEAD: &'static str = "head";
+    const OPTIONS: &'static str = "options";
+    const WEBSOCKET: &'static str = "websocket";
+    const FALLBACK: &'static str = "fallback";
+    const LAYER: &'static str = "layer";
     const DOC: &'static str = "doc";
+    const BODY: &'static str = "body";
+    const QUERY: &'static str = "query";
+    const HEADER: &'static str = "header";
     const SERVER: &'static str = "server";
+    const HOST: &'static str = "host";
+    const URLS: &'static str = "urls";
+    pub const CORS: &'static str = "cors";
 
     pub fn new() -> Self {
         Fixture {
@@ -31,18 +49,72 @@ impl Fixture {
     }
 
     pub fn is_method_attr(&self, attr: &syn::Attribute) -> bool {
-        attr.path.is_ident(Self::GET)
+        self.http_method(&attr.path).is_some()
+            || self.is_fallback(&attr.path)
+            || self.is_layer(&attr.path)
     }
 
     pub fn is_arg_attr(&self, attr: &syn::Attribute) -> bool {
-        attr.path.is_ident(Self::DOC)
+        self.is_doc(&attr.path)
+            || self.is_body(&attr.path)
+            || self.is_query(&attr.path)
+            || self.is_header(&attr.path)
+    }
+
+    pub fn is_doc(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::DOC)
+    }
+
+    pub fn is_body(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::BODY)
+    }
+
+    pub fn is_query(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::QUERY)
+    }
+
+    pub fn is_header(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::HEADER)
     }
 
-    pub fn is_get(&self, p: &syn::Path) -> bool {
-        p.is_ident(Self::GET)
+    pub fn http_method(&self, p: &syn::Path) -> Option<Method> {
+        [
+            (Self::GET, Method::Get),
+            (Self::POST, Method::Post),
+            (Self::PUT, Method::Put),
+            (Self::PATCH, Method::Patch),
+            (Self::DELETE, Method::Delete),
+            (Self::HEAD, Method::Head),
+            (Self::OPTIONS, Method::Options),
+            // WebSocket handshake is always a GET request
+            (Self::WEBSOCKET, Method::Get),
+        ]
+        .iter()
+        .find(|(name, _)| p.is_ident(name))
+        .map(|(_, method)| *method)
+    }
+
+    pub fn is_websocket(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::WEBSOCKET)
+    }
+
+    pub fn is_fallback(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::FALLBACK)
+    }
+
+    pub fn is_layer(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::LAYER)
     }
 
     pub fn is_server(&self, p: &syn::Path) -> bool {
         p.is_ident(Self::SERVER)
     }
+
+    pub fn is_host(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::HOST)
+    }
+
+    pub fn is_urls(&self, p: &syn::Path) -> bool {
+        p.is_ident(Self::URLS)
+    }
 }
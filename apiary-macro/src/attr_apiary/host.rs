@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use proc_macro_error::emit_error;
+use syn::parse_quote;
+
+/// Host pattern like `{tenant}.example.com`.
+#[derive(Debug)]
+pub struct Host {
+    /// Lowercased labels, where `None` is the parameter.
+    pub labels: Vec<Option<String>>,
+    pub params: HashMap<String, usize>,
+}
+
+pub fn parse_args(lit: &syn::Lit) -> Option<Host> {
+    let pattern = match lit {
+        syn::Lit::Str(lit) => lit.value(),
+        _ => {
+            emit_error!(lit, "Expected a host pattern like \"{tenant}.example.com\"");
+            return None;
+        }
+    };
+
+    let mut params = HashMap::new();
+    let labels = pattern
+        .split('.')
+        .enumerate()
+        .map(|(idx, label)| {
+            if let Some(name) = label.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+                if name.is_empty() {
+                    emit_error!(lit, "Empty host parameter name");
+                } else if params.insert(name.to_owned(), idx).is_some() {
+                    emit_error!(lit, "Duplicated host parameter {}", name);
+                }
+                return None;
+            }
+
+            let valid = label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+            if label.is_empty() {
+                emit_error!(lit, "Empty host label");
+            } else if !valid {
+                emit_error!(lit, "Invalid host label `{}`", label);
+            }
+            Some(label.to_ascii_lowercase())
+        })
+        .collect();
+
+    Some(Host { labels, params })
+}
+
+/// Checks the label count and the labels of the request's host.
+/// The parameters match any label except the empty one.
+pub fn host_matches(host: &Host) -> TokenStream {
+    let len = host.labels.len();
+    let labels = host
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(idx, label)| -> syn::Expr {
+            match label {
+                Some(label) => parse_quote!(host[#idx] == #label),
+                None => parse_quote!(!host[#idx].is_empty()),
+            }
+        });
+
+    quote::quote! {
+        (host.len() == #len #(&& #labels)*)
+    }
+}
@@ -1,28 +1,60 @@
 use std::collections::HashMap;
 
+use http::header::HeaderName;
 use http::Uri;
 use proc_macro_error::emit_error;
 
-use super::extract::Extracted;
+use super::extract::{self, Extracted};
 use super::fixture::Fixture;
+use super::host::Host;
+
+const VERSION: &str = "version";
 
 #[derive(Debug)]
 pub struct Parsed {
+    pub vis: syn::Visibility,
     pub trait_name: syn::Ident,
     pub handlers: Vec<Handler>,
+    pub fallback: Option<Fallback>,
 }
 
 #[derive(Debug)]
 pub struct Handler {
     pub path_attr: syn::Attribute,
     pub name: syn::Ident,
+    pub doc: String,
     pub http_method: Method,
+    /// Declared with `#[websocket]` instead of the HTTP method attribute.
+    pub websocket: bool,
+    /// Path as written in the attribute, like `/pets/{id}`.
+    pub template: String,
+    /// Segments without the trailing slash, where `None` is the parameter.
     pub path: Vec<Option<String>>,
+    pub trailing_slash: bool,
+    /// API version from `#[get("/path", version = "2")]`, served for every version if `None`.
+    pub version: Option<String>,
     pub params: Vec<Param>,
+    pub return_ty: syn::Type,
+    /// Tower layers from `#[layer(...)]`, the outermost first.
+    pub layers: Vec<syn::Expr>,
 }
+
+/// `#[fallback]` method which takes the requests no handler matches.
 #[derive(Debug)]
+pub struct Fallback {
+    pub name: syn::Ident,
+    pub return_ty: syn::Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Method {
     Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
 }
 
 #[derive(Debug)]
@@ -34,131 +66,357 @@ pub struct Param {
 
 #[derive(Debug)]
 pub enum ParamSrc {
-    Path { idx: usize },
+    Path {
+        idx: usize,
+    },
+    /// Label of the `#[api(host = ...)]` pattern.
+    Host {
+        idx: usize,
+    },
+    Query,
+    Header {
+        name: String,
+    },
+    Body,
+    WebSocket,
 }
 
-pub fn parse(extracted: &Extracted, fixture: &Fixture) -> Option<Parsed> {
-    Some(Parsed {
-        trait_name: extracted.trait_name.clone(),
-        handlers: extracted
-            .methods
-            .iter()
-            .filter_map(|method| {
-                let mut http_method = None;
-                let mut path_attr = None;
-
-                for a in &method.attrs {
-                    if fixture.is_get(&a.path) {
-                        http_method = Some(Method::Get);
-                        path_attr = Some(a.clone());
-                    } else {
-                        emit_error!(a, "Unexpected attribute");
-                        return None;
-                    }
+pub fn parse(extracted: &Extracted, fixture: &Fixture, host: Option<&Host>) -> Option<Parsed> {
+    let mut fallback = None;
+
+    let handlers = extracted
+        .methods
+        .iter()
+        .filter_map(|method| {
+            if let Some(attr) = method.attrs.iter().find(|a| fixture.is_fallback(&a.path)) {
+                if fallback.is_some() {
+                    emit_error!(attr, "Only one method can be the #[fallback]");
+                } else {
+                    fallback = parse_fallback(method, attr);
                 }
+                return None;
+            }
 
-                let http_method = http_method?;
-                let path_attr = path_attr?;
+            let mut http_method = None;
+            let mut path_attr = None;
+            let mut layers = vec![];
 
-                let path = match path_attr.parse_meta() {
-                    Ok(syn::Meta::List(list)) if list.nested.len() == 1 => {
-                        list.nested.first().unwrap().clone()
-                    }
-                    _ => {
-                        emit_error!(path_attr, "Failed to parse attribute");
-                        return None;
+            for a in &method.attrs {
+                if fixture.is_layer(&a.path) {
+                    match a.parse_args() {
+                        Ok(layer) => layers.push(layer),
+                        Err(_) => emit_error!(a, "#[layer] takes the tower layer expression"),
                     }
-                };
-                let mut path = match path {
-                    syn::NestedMeta::Lit(syn::Lit::Str(lit)) => lit.value(),
-                    _ => {
-                        emit_error!(path, "Failed to parse attribute");
+                } else if let Some(method) = fixture.http_method(&a.path) {
+                    if http_method.is_some() {
+                        emit_error!(a, "Handler can only have one HTTP method attribute");
                         return None;
                     }
-                };
-                if let Some(idx) = path.find('?') {
-                    emit_error!(path_attr, "URI with query string is not supported");
-                    path.truncate(idx);
+                    http_method = Some(method);
+                    path_attr = Some(a.clone());
+                } else {
+                    emit_error!(a, "Unexpected attribute");
+                    return None;
                 }
-                if let Some(idx) = path.find('#') {
-                    emit_error!(path_attr, "URI with hash fragment is not supported");
-                    path.truncate(idx);
+            }
+
+            let (http_method, path_attr) = match (http_method, path_attr) {
+                (Some(http_method), Some(path_attr)) => (http_method, path_attr),
+                _ => {
+                    emit_error!(method.name, "#[layer] requires the HTTP method attribute");
+                    return None;
+                }
+            };
+            let websocket = fixture.is_websocket(&path_attr.path);
+
+            let mut nested = match path_attr.parse_meta() {
+                Ok(syn::Meta::List(list)) if !list.nested.is_empty() => list.nested.into_iter(),
+                _ => {
+                    emit_error!(path_attr, "Failed to parse attribute");
+                    return None;
                 }
-                let path = path.strip_prefix('/').unwrap_or_else(|| {
-                    emit_error!(path_attr, "URI should be started with `/`");
-                    &path
-                });
-
-                let mut path_params = HashMap::new();
-                let path: Vec<_> = path
-                    .split('/')
-                    .enumerate()
-                    .map(|(idx, seg)| {
-                        if let Some(name) =
-                            seg.strip_prefix('{').and_then(|seg| seg.strip_suffix('}'))
-                        {
-                            path_params.insert(name.to_owned(), idx);
-                            None
-                        } else {
-                            Some(seg.to_owned())
+            };
+            let path = nested.next()?;
+
+            let mut version = None;
+            for arg in nested {
+                match arg {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    })) if path.is_ident(VERSION) && version.is_none() => {
+                        let value = lit.value();
+                        let valid = value.starts_with(|c: char| c.is_ascii_digit())
+                            && value
+                                .bytes()
+                                .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-');
+                        if !valid {
+                            emit_error!(lit, "Invalid version, expected like \"2\"");
                         }
-                    })
-                    .collect();
-
-                let path_check: String = path
-                    .iter()
-                    .filter_map(|seg| seg.as_deref())
-                    .map(|seg| format!("/{}", seg))
-                    .collect();
-                if path_check.parse::<Uri>().is_err() {
-                    emit_error!(path_attr, "Invalid URI");
+                        version = Some(value);
+                    }
+                    other => emit_error!(other, "Invalid parameter"),
+                }
+            }
+            let mut path = match path {
+                syn::NestedMeta::Lit(syn::Lit::Str(lit)) => lit.value(),
+                _ => {
+                    emit_error!(path, "Failed to parse attribute");
+                    return None;
                 }
+            };
+            if let Some(idx) = path.find('?') {
+                emit_error!(path_attr, "URI with query string is not supported");
+                path.truncate(idx);
+            }
+            if let Some(idx) = path.find('#') {
+                emit_error!(path_attr, "URI with hash fragment is not supported");
+                path.truncate(idx);
+            }
+            let path = path.strip_prefix('/').unwrap_or_else(|| {
+                emit_error!(path_attr, "URI should be started with `/`");
+                &path
+            });
 
-                let params: Vec<_> = method
-                    .args
-                    .iter()
-                    .filter_map(|arg| {
-                        if let Some(idx) = path_params.remove(&arg.name.to_string()) {
-                            Some(Param {
-                                name: arg.name.clone(),
-                                ty: arg.ty.clone(),
-                                src: ParamSrc::Path { idx },
-                            })
-                        } else {
+            let template = format!("/{}", path);
+            let raw_path = path.replace(['{', '}'], "");
+            // the root path `/` has no segment, just like the requested one
+            let (path, trailing_slash) = match path.strip_suffix('/') {
+                Some(path) => (path, !path.is_empty()),
+                None => (path, false),
+            };
+
+            let mut path_params = HashMap::new();
+            let path: Vec<_> = path
+                .split('/')
+                .filter(|_| !path.is_empty())
+                .enumerate()
+                .map(|(idx, seg)| {
+                    if let Some(name) = seg.strip_prefix('{').and_then(|seg| seg.strip_suffix('}'))
+                    {
+                        path_params.insert(name.to_owned(), idx);
+                        None
+                    } else {
+                        if matches!(seg, "" | "." | "..") {
                             emit_error!(
-                                arg.name,
-                                "Fn parameter {} is not found from the URI parameters",
-                                arg.name
+                                path_attr,
+                                "URI should not contain empty, `.` or `..` segments"
                             );
-                            None
                         }
-                    })
-                    .collect();
-
-                for param in path_params.keys() {
-                    emit_error!(
-                        path_attr,
-                        "Parameter {} not found from the function parameters",
-                        param
-                    );
-                }
+                        // compared with the decoded segment of the request
+                        Some(percent_decode(seg).unwrap_or_else(|| {
+                            emit_error!(path_attr, "Invalid percent-encoding in URI");
+                            seg.to_owned()
+                        }))
+                    }
+                })
+                .collect();
 
-                Some(Handler {
-                    path_attr,
-                    name: method.name.clone(),
-                    http_method,
-                    path,
-                    params,
+            if format!("/{}", raw_path).parse::<Uri>().is_err() {
+                emit_error!(path_attr, "Invalid URI");
+            }
+
+            let mut has_body = false;
+            let mut has_socket = false;
+            let params: Vec<_> = method
+                .args
+                .iter()
+                .filter_map(|arg| {
+                    if let Some(attr) = arg.attrs.iter().find(|a| fixture.is_body(&a.path)) {
+                        if !attr.tokens.is_empty() {
+                            emit_error!(attr, "#[body] doesn't take any parameter");
+                        }
+                        if websocket {
+                            emit_error!(attr, "WebSocket handler can't take #[body] parameter");
+                            return None;
+                        }
+                        if has_body {
+                            emit_error!(arg.name, "Handler can only take one #[body] parameter");
+                            return None;
+                        }
+                        has_body = true;
+
+                        Some(Param {
+                            name: arg.name.clone(),
+                            ty: arg.ty.clone(),
+                            src: ParamSrc::Body,
+                        })
+                    } else if let Some(attr) = arg.attrs.iter().find(|a| fixture.is_query(&a.path))
+                    {
+                        if !attr.tokens.is_empty() {
+                            emit_error!(attr, "#[query] doesn't take any parameter");
+                        }
+
+                        Some(Param {
+                            name: arg.name.clone(),
+                            ty: arg.ty.clone(),
+                            src: ParamSrc::Query,
+                        })
+                    } else if let Some(attr) = arg.attrs.iter().find(|a| fixture.is_header(&a.path))
+                    {
+                        let name = parse_header_name(attr, &arg.name)?;
+
+                        Some(Param {
+                            name: arg.name.clone(),
+                            ty: arg.ty.clone(),
+                            src: ParamSrc::Header { name },
+                        })
+                    } else if let Some(idx) = path_params.remove(&arg.name.to_string()) {
+                        Some(Param {
+                            name: arg.name.clone(),
+                            ty: arg.ty.clone(),
+                            src: ParamSrc::Path { idx },
+                        })
+                    } else if let Some(&idx) =
+                        host.and_then(|host| host.params.get(&arg.name.to_string()))
+                    {
+                        Some(Param {
+                            name: arg.name.clone(),
+                            ty: arg.ty.clone(),
+                            src: ParamSrc::Host { idx },
+                        })
+                    } else if websocket && !has_socket {
+                        has_socket = true;
+
+                        Some(Param {
+                            name: arg.name.clone(),
+                            ty: arg.ty.clone(),
+                            src: ParamSrc::WebSocket,
+                        })
+                    } else {
+                        emit_error!(
+                            arg.name,
+                            "Fn parameter {} is not found from the URI parameters",
+                            arg.name
+                        );
+                        None
+                    }
                 })
+                .collect();
+
+            if websocket && !has_socket {
+                emit_error!(
+                    method.name,
+                    "WebSocket handler should take the apiary::ws::WebSocket parameter"
+                );
+            }
+
+            for param in path_params.keys() {
+                emit_error!(
+                    path_attr,
+                    "Parameter {} not found from the function parameters",
+                    param
+                );
+            }
+
+            Some(Handler {
+                path_attr,
+                name: method.name.clone(),
+                doc: method.doc.clone(),
+                http_method,
+                websocket,
+                template,
+                path,
+                trailing_slash,
+                version,
+                params,
+                return_ty: method.return_ty.clone(),
+                layers,
             })
-            .collect(),
+        })
+        .collect();
+
+    Some(Parsed {
+        vis: extracted.vis.clone(),
+        trait_name: extracted.trait_name.clone(),
+        handlers,
+        fallback,
+    })
+}
+
+fn parse_fallback(method: &extract::Method, attr: &syn::Attribute) -> Option<Fallback> {
+    if !attr.tokens.is_empty() {
+        emit_error!(attr, "#[fallback] doesn't take any parameter");
+    }
+    if let Some(other) = method.attrs.iter().find(|a| *a != attr) {
+        emit_error!(other, "#[fallback] method can't have other attributes");
+        return None;
+    }
+
+    match &*method.args {
+        [arg] if arg.attrs.is_empty() => {}
+        _ => {
+            emit_error!(
+                method.name,
+                "#[fallback] method should take the request as its only parameter"
+            );
+            return None;
+        }
+    }
+
+    Some(Fallback {
+        name: method.name.clone(),
+        return_ty: method.return_ty.clone(),
     })
 }
 
+/// Takes the header name from `#[header("name")]`,
+/// or from the parameter name like `last_event_id` for the `#[header]`.
+fn parse_header_name(attr: &syn::Attribute, arg_name: &syn::Ident) -> Option<String> {
+    let name = if attr.tokens.is_empty() {
+        arg_name.to_string().replace('_', "-")
+    } else {
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) if list.nested.len() == 1 => match list.nested.first() {
+                Some(syn::NestedMeta::Lit(syn::Lit::Str(lit))) => lit.value(),
+                _ => {
+                    emit_error!(attr, "#[header] only takes the header name as a string");
+                    return None;
+                }
+            },
+            _ => {
+                emit_error!(attr, "#[header] only takes the header name as a string");
+                return None;
+            }
+        }
+    };
+
+    match HeaderName::from_bytes(name.as_bytes()) {
+        Ok(name) => Some(name.as_str().to_owned()),
+        Err(_) => {
+            emit_error!(attr, "Invalid header name {}", name);
+            None
+        }
+    }
+}
+
 impl Method {
     pub fn ident(&self) -> syn::Ident {
         match self {
             Self::Get => quote::format_ident!("GET"),
+            Self::Post => quote::format_ident!("POST"),
+            Self::Put => quote::format_ident!("PUT"),
+            Self::Patch => quote::format_ident!("PATCH"),
+            Self::Delete => quote::format_ident!("DELETE"),
+            Self::Head => quote::format_ident!("HEAD"),
+            Self::Options => quote::format_ident!("OPTIONS"),
+        }
+    }
+}
+
+fn percent_decode(seg: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(seg.len());
+    let mut iter = seg.bytes();
+
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = |b: Option<u8>| (b? as char).to_digit(16);
+            bytes.push((hex(iter.next())? * 16 + hex(iter.next())?) as u8);
+        } else {
+            bytes.push(b);
         }
     }
+
+    String::from_utf8(bytes).ok()
 }
@@ -0,0 +1,81 @@
+use proc_macro2::TokenStream;
+use syn::parse_quote;
+
+use crate::attr_apiary::parse::{Handler, ParamSrc, Parsed};
+
+/// Generates the trait method which describes the handlers.
+pub fn codegen(parsed: &Parsed) -> syn::TraitItem {
+    let routes = parsed.handlers.iter().map(codegen_route);
+
+    parse_quote! {
+        /// Routes of the handlers, in the order they're declared.
+        fn routes() -> &'static [apiary::RouteInfo]
+        where
+            Self: Sized,
+        {
+            static ROUTES: &[apiary::RouteInfo] = &[#(#routes),*];
+            ROUTES
+        }
+    }
+}
+
+fn codegen_route(handler: &Handler) -> TokenStream {
+    let method = handler.http_method.ident();
+    let path = &handler.template;
+    let name = handler.name.to_string();
+    let websocket = handler.websocket;
+    let version = match &handler.version {
+        Some(version) => quote::quote!(Some(#version)),
+        None => quote::quote!(None),
+    };
+    let doc = &handler.doc;
+
+    let params = handler.params.iter().map(|param| {
+        let name = param.name.to_string();
+        let ty = type_name(&param.ty);
+        let source = match &param.src {
+            ParamSrc::Path { .. } => quote::quote!(Path),
+            ParamSrc::Host { .. } => quote::quote!(Host),
+            ParamSrc::Query => quote::quote!(Query),
+            ParamSrc::Header { name } => quote::quote!(Header(#name)),
+            ParamSrc::Body => quote::quote!(Body),
+            ParamSrc::WebSocket => quote::quote!(WebSocket),
+        };
+
+        quote::quote! {
+            apiary::route::ParamInfo {
+                name: #name,
+                source: apiary::route::ParamSource::#source,
+                ty: #ty,
+            }
+        }
+    });
+
+    quote::quote! {
+        apiary::RouteInfo {
+            method: apiary::http::Method::#method,
+            path: #path,
+            handler: #name,
+            websocket: #websocket,
+            version: #version,
+            params: &[#(#params),*],
+            doc: #doc,
+        }
+    }
+}
+
+/// Prints the type like `Option<String>`, without the spaces between the tokens.
+fn type_name(ty: &syn::Type) -> String {
+    let tokens = quote::quote!(#ty).to_string();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+
+    let mut name = String::new();
+    for token in tokens.split_whitespace() {
+        let after_word = name.ends_with(is_word);
+        if after_word && token.starts_with(is_word) || name.ends_with(',') {
+            name.push(' ');
+        }
+        name.push_str(token);
+    }
+    name
+}
@@ -1,12 +1,70 @@
+use proc_macro2::TokenStream;
 use proc_macro_error::emit_error;
 use syn::parse_quote;
 use syn::spanned::Spanned;
 
-use crate::attr_apiary::parse::{Handler, Method, Param, ParamSrc, Parsed};
+use crate::attr_apiary::host::{self, Host};
+use crate::attr_apiary::parse::{Fallback, Handler, Method, Param, ParamSrc, Parsed};
+
+const TRAILING_SLASH: &str = "trailing_slash";
+const CASE_SENSITIVE: &str = "case_sensitive";
+const VERSIONING: &str = "versioning";
+const VENDOR: &str = "vendor";
+const DEFAULT_VERSION: &str = "default_version";
 
 #[derive(Debug)]
 pub struct Args {
     type_name: syn::Ident,
+    trailing_slash: TrailingSlash,
+    case_sensitive: bool,
+    versioning: Option<(Versioning, syn::LitStr)>,
+    vendor: Option<String>,
+    /// Version of the requests which don't specify any.
+    default_version: Option<String>,
+}
+
+/// How the trailing slash of the request path is matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrailingSlash {
+    /// `/pets/` only matches with `/pets/`.
+    Strict,
+    /// `/pets/` is redirected to `/pets` and vice versa.
+    Redirect,
+    /// `/pets/` matches with both `/pets` and `/pets/`.
+    Ignore,
+}
+
+/// Where the requested API version is read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Versioning {
+    /// `/v2/pets`
+    Path,
+    /// `Accept-Version: 2`
+    Header,
+    /// `Accept: application/vnd.acme.v2+json`
+    MediaType,
+}
+
+/// Whether the generated code serves the request or only recognizes its handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Renders the rejections with the `render_rejection()`.
+    Serve,
+    /// Returns the rejections as the `Err`.
+    Recognize,
+}
+
+impl Mode {
+    fn reject(self, rejection: TokenStream) -> TokenStream {
+        match self {
+            Mode::Serve => quote::quote! {
+                T::render_rejection(&*self.0, #rejection.into())
+            },
+            Mode::Recognize => quote::quote! {
+                Err(#rejection.into())
+            },
+        }
+    }
 }
 
 pub fn parse_args(args: impl IntoIterator<Item = syn::NestedMeta> + Spanned) -> Option<Args> {
@@ -32,52 +90,960 @@ pub fn parse_args(args: impl IntoIterator<Item = syn::NestedMeta> + Spanned) ->
         }
     };
 
-    if args.next().is_some() {
-        emit_error!(span, "Too many attribute parameters, server only takes one");
+    let mut parsed = Args {
+        type_name,
+        trailing_slash: TrailingSlash::Strict,
+        case_sensitive: true,
+        versioning: None,
+        vendor: None,
+        default_version: None,
+    };
+
+    for arg in args {
+        let nv = match arg {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => nv,
+            other => {
+                emit_error!(other, "Invalid server parameter");
+                continue;
+            }
+        };
+
+        match (nv.path.get_ident().map(|id| id.to_string()), &nv.lit) {
+            (Some(name), syn::Lit::Str(lit)) if name == TRAILING_SLASH => {
+                parsed.trailing_slash = match &*lit.value() {
+                    "strict" => TrailingSlash::Strict,
+                    "redirect" => TrailingSlash::Redirect,
+                    "ignore" => TrailingSlash::Ignore,
+                    _ => {
+                        emit_error!(
+                            lit,
+                            "Expected one of \"strict\", \"redirect\" or \"ignore\""
+                        );
+                        continue;
+                    }
+                }
+            }
+            (Some(name), syn::Lit::Bool(lit)) if name == CASE_SENSITIVE => {
+                parsed.case_sensitive = lit.value;
+            }
+            (Some(name), syn::Lit::Str(lit)) if name == VERSIONING => {
+                let versioning = match &*lit.value() {
+                    "path" => Versioning::Path,
+                    "header" => Versioning::Header,
+                    "media_type" => Versioning::MediaType,
+                    _ => {
+                        emit_error!(
+                            lit,
+                            "Expected one of \"path\", \"header\" or \"media_type\""
+                        );
+                        continue;
+                    }
+                };
+                parsed.versioning = Some((versioning, lit.clone()));
+            }
+            (Some(name), syn::Lit::Str(lit)) if name == VENDOR => {
+                parsed.vendor = Some(lit.value().to_ascii_lowercase());
+            }
+            (Some(name), syn::Lit::Str(lit)) if name == DEFAULT_VERSION => {
+                parsed.default_version = Some(lit.value());
+            }
+            _ => emit_error!(nv, "Invalid server parameter"),
+        }
     }
 
-    Some(Args { type_name })
+    match &parsed.versioning {
+        Some((Versioning::MediaType, lit)) if parsed.vendor.is_none() => {
+            emit_error!(lit, "media_type versioning requires the vendor parameter");
+        }
+        Some((Versioning::Path, _)) | Some((Versioning::Header, _)) if parsed.vendor.is_some() => {
+            emit_error!(span, "vendor parameter requires the media_type versioning");
+        }
+        None if parsed.default_version.is_some() => {
+            emit_error!(
+                span,
+                "default_version parameter requires the versioning parameter"
+            );
+        }
+        _ => {}
+    }
+
+    Some(parsed)
+}
+
+impl Args {
+    /// Whether the version is the path prefix like `/v2/pets`.
+    pub fn path_versioning(&self) -> bool {
+        matches!(self.versioning, Some((Versioning::Path, _)))
+    }
 }
 
-pub fn codegen(args: Args, parsed: &Parsed) -> Vec<syn::Item> {
-    let handlers: Vec<_> = parsed.handlers.iter().map(codegen_handler).collect();
+pub fn codegen(
+    args: Args,
+    parsed: &Parsed,
+    host: Option<&Host>,
+    cors: Option<TokenStream>,
+) -> Vec<syn::Item> {
+    let (handlers, layered): (Vec<_>, Vec<_>) = parsed
+        .handlers
+        .iter()
+        .map(|handler| codegen_handler(&args, handler))
+        .unzip();
+    let layered: Vec<_> = layered.into_iter().flatten().collect();
+    // the matched handler inserts its route into the request extensions
+    let parts = if !parsed.handlers.is_empty() {
+        quote::quote!(mut parts)
+    } else {
+        quote::quote!(parts)
+    };
+
+    let (invalid_param, reject_invalid_param) = invalid_params(parsed, Mode::Serve);
+
+    let allow_methods = allow_methods(&args, parsed);
+    let redirect = if args.trailing_slash == TrailingSlash::Redirect {
+        let toggled_matches = parsed
+            .handlers
+            .iter()
+            .map(|handler| path_matches(&args, handler, true));
+
+        quote::quote! {
+            if allow.is_empty() && (false #(|| #toggled_matches)*) {
+                let location = parts.uri.path();
+                let location = match location.strip_suffix('/') {
+                    Some(location) => location.to_owned(),
+                    None => format!("{}/", location),
+                };
+                let location = match parts.uri.query() {
+                    Some(query) => format!("{}?{}", location, query),
+                    None => location,
+                };
+                return apiary::response::Response::into_response(apiary::server::Redirect { location });
+            }
+        }
+    } else {
+        quote::quote!()
+    };
+    let method_not_allowed =
+        Mode::Serve.reject(quote::quote!(apiary::rejection::MethodNotAllowed {
+            request,
+            allow
+        }));
+    let fallback = if parsed.handlers.is_empty() {
+        quote::quote! {
+            let request = apiary::http::Request::from_parts(parts, body);
+            #reject_invalid_param
+        }
+    } else {
+        quote::quote! {
+            #allow_methods
+            #redirect
+
+            let request = apiary::http::Request::from_parts(parts, body);
+            #reject_invalid_param
+
+            if request.method() == apiary::http::Method::HEAD
+                && allow.contains(&apiary::http::Method::HEAD)
+            {
+                let mut request = request;
+                *request.method_mut() = apiary::http::Method::GET;
+                let resp = apiary::server::Server::serve(self, request).await?;
+                // keeps the headers including the Content-Length
+                let (parts, _) = resp.into_parts();
+                return Ok(apiary::http::Response::from_parts(parts, apiary::response::Body::empty()));
+            }
+            if request.method() == apiary::http::Method::OPTIONS && !allow.is_empty() {
+                return apiary::response::Response::into_response(apiary::server::Options { allow });
+            }
+            if !allow.is_empty() {
+                return #method_not_allowed;
+            }
+        }
+    };
+
+    let prelude = prelude(&args, parsed, host, Mode::Serve);
+    let not_found = not_found(parsed, Mode::Serve, quote::quote!(request));
+    let serve = quote::quote! {
+        Box::pin(async move {
+            let (#parts, body) = request.into_parts();
+            let body = apiary::request::boxed(body);
+            #prelude
+            #invalid_param
+
+            #(#handlers)*
+
+            #fallback
+            #not_found
+        })
+    };
+    let serve = match cors {
+        // built once, as the configuration is fixed at compile time
+        Some(config) => quote::quote! {
+            static CORS: std::sync::OnceLock<apiary::cors::CorsConfig> = std::sync::OnceLock::new();
+            CORS.get_or_init(|| #config).serve(request, move |request| #serve)
+        },
+        None => serve,
+    };
 
-    let type_name = args.type_name;
+    let vis = &parsed.vis;
+    let type_name = &args.type_name;
     let trait_name = &parsed.trait_name;
     let type_def: syn::Item = parse_quote! {
         #[derive(Debug)]
-        struct #type_name<T: ?Sized>(pub std::sync::Arc<T>);
+        #vis struct #type_name<T: ?Sized>(pub std::sync::Arc<T>);
     };
     let impl_clone: syn::Item = parse_quote! {
         impl<T: ?Sized> std::clone::Clone for #type_name<T> {
             fn clone(&self) -> Self {
-                #type_name(Arc::clone(&self.0))
+                #type_name(std::sync::Arc::clone(&self.0))
             }
         }
     };
 
     let impl_server: syn::Item = parse_quote! {
-        impl<T: Foo + ?Sized> apiary::server::Server for #type_name<T> {
+        impl<T> apiary::server::Server for #type_name<T>
+        where
+            T: #trait_name + Send + Sync + ?Sized + 'static,
+        {
             fn serve<B>(self, request: apiary::http::Request<B>) -> apiary::server::ServeResult
             where
-                B: apiary::http_body::Body,
+                B: apiary::http_body::Body + Send + Sync + 'static,
+                B::Error: std::convert::Into<apiary::BoxError>,
+            {
+                #serve
+            }
+        }
+    };
+
+    let mut items = vec![type_def, impl_clone, impl_server];
+    if !layered.is_empty() {
+        items.push(parse_quote! {
+            impl<T> #type_name<T>
+            where
+                T: #trait_name + Send + Sync + ?Sized + 'static,
             {
-                Box::pin(async move {
-                    #(#handlers)*
+                #(#layered)*
+            }
+        });
+    }
+    items.append(&mut codegen_route(&args, parsed, host));
+    items
+}
+
+/// Generates the `FooRoute` enum which recognizes the handler the request targets.
+fn codegen_route(args: &Args, parsed: &Parsed, host: Option<&Host>) -> Vec<syn::Item> {
+    let vis = &parsed.vis;
+    let route_name = quote::format_ident!("{}Route", parsed.trait_name);
+    let doc = format!(
+        "Handler of the [`{}`] the request targets, with its parameters except the body.",
+        parsed.trait_name
+    );
+
+    let variants: Vec<_> = parsed
+        .handlers
+        .iter()
+        .map(|handler| {
+            let variant = variant_name(&handler.name);
+            let fields: Vec<_> = route_fields(handler).collect();
+            (variant, fields)
+        })
+        .collect();
+
+    let decl_variants = variants.iter().map(|(variant, fields)| {
+        if fields.is_empty() {
+            return quote::quote!(#variant);
+        }
+        let names = fields.iter().map(|param| &param.name);
+        let tys = fields.iter().map(|param| &param.ty);
+        quote::quote!(#variant { #(#names: #tys),* })
+    });
+    let names = parsed
+        .handlers
+        .iter()
+        .map(|handler| handler.name.to_string());
+    let name_arms = variants.iter().zip(names).map(|((variant, fields), name)| {
+        let pat = if fields.is_empty() {
+            quote::quote!(#route_name::#variant)
+        } else {
+            quote::quote!(#route_name::#variant { .. })
+        };
+        quote::quote!(#pat => #name)
+    });
+
+    // HEAD requests are recognized as the GET handler, unless a HEAD handler matches
+    let head_handlers: Vec<_> = parsed
+        .handlers
+        .iter()
+        .filter(|handler| handler.http_method == Method::Head)
+        .map(|handler| path_matches(args, handler, false))
+        .collect();
+    let has_get = parsed
+        .handlers
+        .iter()
+        .any(|handler| handler.http_method == Method::Get && !handler.websocket);
+    let head_as_get = has_get.then(|| {
+        quote::quote! {
+            let head_as_get = parts.method == apiary::http::Method::HEAD
+                && !(false #(|| #head_handlers)*);
+        }
+    });
+
+    let handlers = parsed
+        .handlers
+        .iter()
+        .zip(&variants)
+        .map(|(handler, (variant, fields))| {
+            let method = handler.http_method.ident();
+            let path_matches = path_matches(args, handler, false);
+            let method_matches = if handler.http_method == Method::Get && !handler.websocket {
+                quote::quote!((parts.method == apiary::http::Method::#method || head_as_get))
+            } else {
+                quote::quote!(parts.method == apiary::http::Method::#method)
+            };
+
+            let mut parse_params = vec![];
+            let mut decode = vec![];
+            for param in &handler.params {
+                match &param.src {
+                    ParamSrc::Path { .. } | ParamSrc::Host { .. } => parse_params.push(param),
+                    ParamSrc::Query => {
+                        decode.push(codegen_query(Mode::Recognize, &param.name, &param.ty))
+                    }
+                    ParamSrc::Header { name } => {
+                        decode.push(codegen_header(
+                            Mode::Recognize,
+                            &param.name,
+                            &param.ty,
+                            name,
+                        ));
+                    }
+                    ParamSrc::Body | ParamSrc::WebSocket => {}
+                }
+            }
+            let names = fields.iter().map(|param| &param.name);
+            let route = if fields.is_empty() {
+                quote::quote!(#route_name::#variant)
+            } else {
+                quote::quote!(#route_name::#variant { #(#names),* })
+            };
+            let call = parse_params_then(
+                parse_params,
+                quote::quote! {
+                    #(#decode)*
+                    return Ok(#route);
+                },
+            );
+
+            quote::quote! {
+                if #method_matches && #path_matches {
+                    #call
+                }
+            }
+        });
+
+    let prelude = prelude(args, parsed, host, Mode::Recognize);
+    let (invalid_param, reject_invalid_param) = invalid_params(parsed, Mode::Recognize);
+    let (allow_methods, method_not_allowed) = if parsed.handlers.is_empty() {
+        (quote::quote!(), quote::quote!())
+    } else {
+        (
+            allow_methods(args, parsed),
+            quote::quote! {
+                if !allow.is_empty() {
+                    return Err(apiary::rejection::MethodNotAllowed { request, allow }.into());
+                }
+            },
+        )
+    };
+
+    let type_def: syn::Item = parse_quote! {
+        #[doc = #doc]
+        #vis enum #route_name {
+            #(#decl_variants,)*
+        }
+    };
+    let impl_route: syn::Item = parse_quote! {
+        impl #route_name {
+            /// Recognizes the handler the request targets, without calling it.
+            ///
+            /// The rejections keep the request without its body and extensions.
+            pub fn recognize<B>(
+                request: &apiary::http::Request<B>,
+            ) -> std::result::Result<Self, apiary::rejection::Rejection> {
+                let parts = apiary::request::clone_head(request);
+                let body: apiary::request::BoxBody = std::default::Default::default();
+                #prelude
+                #invalid_param
+                #head_as_get
+
+                #(#handlers)*
+
+                #allow_methods
+                let request = apiary::http::Request::from_parts(parts, body);
+                #reject_invalid_param
+                #method_not_allowed
+                Err(apiary::rejection::NotFound { request }.into())
+            }
+
+            /// Name of the handler method.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#name_arms,)*
+                }
+            }
+        }
+    };
+
+    vec![type_def, impl_route]
+}
+
+/// Fields of the route variant, which are the parameters except the body.
+fn route_fields(handler: &Handler) -> impl Iterator<Item = &Param> {
+    handler
+        .params
+        .iter()
+        .filter(|param| !matches!(param.src, ParamSrc::Body | ParamSrc::WebSocket))
+}
+
+/// `list_pets` to `ListPets`.
+fn variant_name(name: &syn::Ident) -> syn::Ident {
+    let name: String = name
+        .to_string()
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    syn::Ident::new(&name, proc_macro2::Span::call_site())
+}
 
-                    let fallback = apiary::http::Response::builder()
-                        .status(404)
-                        .body(apiary::response::Body::once("404 Not Found"))?;
+/// Checks the host, splits the path and reads the version, before matching the handlers.
+fn prelude(args: &Args, parsed: &Parsed, host: Option<&Host>, mode: Mode) -> TokenStream {
+    let request = quote::quote!(apiary::http::Request::from_parts(parts, body));
 
-                    Ok(fallback)
-                })
+    // the whole trait is served only for the matching host
+    let check_host = host.map(|host| {
+        let host_matches = host::host_matches(host);
+        let not_found = not_found(parsed, mode, request.clone());
+        quote::quote! {
+            let host = apiary::server::request_host(&parts).unwrap_or_default();
+            let host: std::vec::Vec<&str> = host.split('.').collect();
+            if !#host_matches {
+                return #not_found;
             }
         }
+    });
+    // the path prefix of the version is removed before matching the handlers
+    let path = match args.versioning {
+        Some(_) => quote::quote!(mut path),
+        None => quote::quote!(path),
+    };
+    let request_path = match args.trailing_slash {
+        TrailingSlash::Ignore => quote::quote! {
+            apiary::server::RequestPath { segments: #path, .. }
+        },
+        TrailingSlash::Strict | TrailingSlash::Redirect => quote::quote! {
+            apiary::server::RequestPath { segments: #path, trailing_slash }
+        },
+    };
+    let invalid_path =
+        mode.reject(quote::quote!(apiary::rejection::InvalidPath { request: #request, error }));
+    let check_version = args
+        .versioning
+        .as_ref()
+        .map(|(versioning, _)| codegen_version(args, *versioning, parsed, mode));
+
+    quote::quote! {
+        #check_host
+        let #request_path = match apiary::server::RequestPath::parse(parts.uri.path()) {
+            Ok(path) => path,
+            Err(error) => return #invalid_path,
+        };
+        #check_version
+    }
+}
+
+/// Answers the request no handler matches,
+/// by the `#[fallback]` method if any and the `NotFound` rejection otherwise.
+fn not_found(parsed: &Parsed, mode: Mode, request: TokenStream) -> TokenStream {
+    match (&parsed.fallback, mode) {
+        (Some(Fallback { name, return_ty }), Mode::Serve) => quote::quote! {{
+            let request = #request;
+            // the fallback's response is negotiated by the request's headers
+            let head = apiary::request::clone_head(&request);
+            let resp: #return_ty = T::#name(self.0, request).await;
+            apiary::response::Response::into_response_for(resp, &head)
+        }},
+        _ => mode.reject(quote::quote!(apiary::rejection::NotFound { request: #request })),
+    }
+}
+
+/// Reads the requested version, and rejects the versions no handler declares.
+fn codegen_version(
+    args: &Args,
+    versioning: Versioning,
+    parsed: &Parsed,
+    mode: Mode,
+) -> TokenStream {
+    let mut versions: Vec<_> = parsed
+        .handlers
+        .iter()
+        .filter_map(|handler| handler.version.as_deref())
+        .collect();
+    versions.sort_unstable();
+    versions.dedup();
+
+    let source = match versioning {
+        Versioning::Path => quote::quote!(Path),
+        Versioning::Header => quote::quote!(Header),
+        Versioning::MediaType => {
+            let vendor = args.vendor.as_deref().unwrap_or_default();
+            quote::quote!(MediaType { vendor: #vendor })
+        }
+    };
+    let default_version = args
+        .default_version
+        .as_ref()
+        .map(|version| quote::quote!(.or_else(|| Some(#version.to_owned()))));
+    let request = quote::quote!(apiary::http::Request::from_parts(parts, body));
+    // the unknown version in the path is just an unknown path
+    let reject = match versioning {
+        Versioning::Path => not_found(parsed, mode, request),
+        Versioning::Header | Versioning::MediaType => mode.reject(quote::quote! {
+            apiary::rejection::UnsupportedVersion {
+                request: #request,
+                version: version.clone(),
+            }
+        }),
+    };
+
+    quote::quote! {
+        let version: std::option::Option<std::string::String> =
+            apiary::server::Versioning::#source.request_version(&parts.headers, &mut path)
+            #default_version;
+        if let Some(version) = &version {
+            if ![#(#versions),*].contains(&version.as_str()) {
+                return #reject;
+            }
+        }
+    }
+}
+
+/// Collects the methods of the handlers which match the path into the `allow`,
+/// regardless of the request's method.
+fn allow_methods(args: &Args, parsed: &Parsed) -> TokenStream {
+    let allow_methods = parsed.handlers.iter().map(|handler| {
+        let mut methods = vec![handler.http_method.ident()];
+        if handler.http_method == Method::Get && !handler.websocket {
+            // answered by the GET handler without the body
+            methods.push(Method::Head.ident());
+        }
+        let path_matches = path_matches(args, handler, false);
+
+        quote::quote! {
+            if #path_matches {
+                #(
+                    if !allow.contains(&apiary::http::Method::#methods) {
+                        allow.push(apiary::http::Method::#methods);
+                    }
+                )*
+            }
+        }
+    });
+
+    quote::quote! {
+        let mut allow = std::vec::Vec::new();
+        #(#allow_methods)*
+        if !allow.is_empty() && !allow.contains(&apiary::http::Method::OPTIONS) {
+            allow.push(apiary::http::Method::OPTIONS);
+        }
+    }
+}
+
+/// Generates the handler call, and the method calling the layered handler if any.
+fn codegen_handler(args: &Args, handler: &Handler) -> (syn::Stmt, Option<TokenStream>) {
+    let method = handler.http_method.ident();
+    let name = &handler.name;
+    let return_ty = &handler.return_ty;
+    let path_matches = path_matches(args, handler, false);
+
+    if handler.version.is_some() && args.versioning.is_none() {
+        emit_error!(
+            handler.path_attr,
+            "version requires the versioning parameter of the server"
+        );
+    }
+
+    let names: Vec<_> = handler.params.iter().map(|param| &param.name).collect();
+    let mut parse_params = vec![];
+    let mut decode_query = vec![];
+    let mut decode_headers = vec![];
+    let mut decode_body = None;
+    let mut socket = None;
+
+    for param in &handler.params {
+        let name = &param.name;
+        let ty = &param.ty;
+
+        match &param.src {
+            ParamSrc::Path { .. } | ParamSrc::Host { .. } => parse_params.push(param),
+            ParamSrc::Query => decode_query.push(codegen_query(Mode::Serve, name, ty)),
+            ParamSrc::Header { name: header } => {
+                decode_headers.push(codegen_header(Mode::Serve, name, ty, header));
+            }
+            ParamSrc::WebSocket => socket = Some(name),
+            ParamSrc::Body => {
+                decode_body = Some(quote::quote! {
+                    if let Some(content_type) = parts.headers.get(apiary::http::header::CONTENT_TYPE) {
+                        if !<#ty as apiary::request::Body>::accepts(content_type.to_str().unwrap_or("")) {
+                            return T::render_rejection(
+                                &*self.0,
+                                apiary::rejection::UnsupportedMediaType {
+                                    request: apiary::http::Request::from_parts(parts, body),
+                                    expected: <#ty as apiary::request::Body>::CONTENT_TYPE,
+                                }.into(),
+                            );
+                        }
+                    }
+                    let #name = match <#ty as apiary::request::Body>::decode_request(&parts, body).await {
+                        Ok(body) => body,
+                        Err(err) => {
+                            // the body is already consumed
+                            let request = apiary::http::Request::from_parts(parts, std::default::Default::default());
+                            return T::render_rejection(
+                                &*self.0,
+                                apiary::rejection::Rejection::from_body_error(request, err),
+                            );
+                        }
+                    };
+                });
+            }
+        }
+    }
+
+    // the WebSocket handler takes the connection upgrade out of the request
+    let mut_parts = if socket.is_some() {
+        quote::quote!(mut parts)
+    } else {
+        quote::quote!(parts)
+    };
+    let call = match socket {
+        // the handler runs on its own task after the connection is upgraded
+        Some(socket) => quote::quote_spanned! {handler.path_attr.span()=>
+            #(#decode_query)*
+            #(#decode_headers)*
+            let upgrade = match apiary::ws::Upgrade::from_request(&mut parts) {
+                Ok(upgrade) => upgrade,
+                Err(error) => {
+                    return T::render_rejection(
+                        &*self.0,
+                        apiary::rejection::InvalidUpgrade {
+                            request: apiary::http::Request::from_parts(parts, body),
+                            error,
+                        }.into(),
+                    );
+                }
+            };
+            let this = self.0;
+            return upgrade.on_upgrade(move |#socket| T::#name(this, #(#names),*));
+        },
+        None => quote::quote_spanned! {handler.path_attr.span()=>
+            #(#decode_query)*
+            #(#decode_headers)*
+            #decode_body
+            let resp: #return_ty = T::#name(self.0, #(#names),*).await;
+            return apiary::response::Response::into_response_for(resp, &parts);
+        },
+    };
+    let (call, layered_method) = if handler.layers.is_empty() {
+        (call, None)
+    } else {
+        // the stack doesn't depend on the server, so it's built only once
+        // and the handler is passed through the request extensions
+        let layers = handler.layers.iter().rev();
+        let method = quote::format_ident!("__layered_{}", name);
+        let params: Vec<_> = parse_params.iter().map(|param| &param.name).collect();
+        let tys = parse_params.iter().map(|param| &param.ty);
+        // the layers may call the handler more than once, like retrying it
+        let clone_params = parse_params.iter().map(|param| {
+            let name = &param.name;
+            quote::quote_spanned!(param.ty.span()=> std::clone::Clone::clone(&#name))
+        });
+
+        let call_layered = quote::quote! {
+            // the first layer is the outermost one
+            static LAYERED: std::sync::OnceLock<apiary::server::Layered> = std::sync::OnceLock::new();
+            let layered = LAYERED.get_or_init(|| {
+                let service = apiary::server::HandlerService;
+                #(let service = apiary::tower::Layer::layer(&(#layers), service);)*
+                apiary::server::Layered::new(service)
+            });
+
+            let server = std::clone::Clone::clone(&self);
+            parts.extensions.insert(apiary::server::Handler::new(move |request| {
+                std::clone::Clone::clone(&server).#method(request, #(#clone_params),*)
+            }));
+            let request = apiary::http::Request::from_parts(parts, body);
+            return layered.call(request).await;
+        };
+
+        let method = quote::quote! {
+            /// Calls the handler with the parameters parsed before the layers.
+            #[doc(hidden)]
+            fn #method(
+                self,
+                request: apiary::rejection::BoxRequest,
+                #(#params: #tys,)*
+            ) -> apiary::server::ServeResult {
+                let (#mut_parts, body) = apiary::http::Request::into_parts(request);
+                Box::pin(async move { #call })
+            }
+        };
+        (call_layered, Some(method))
     };
 
-    vec![type_def, impl_clone, impl_server]
+    let template = &handler.template;
+    let handler_name = name.to_string();
+    let call = quote::quote! {
+        let matched = apiary::MatchedRoute {
+            template: #template,
+            handler: #handler_name,
+        };
+        parts.extensions.insert(matched);
+        // the rejections after the match are labeled with the route as well
+        let resp: std::result::Result<
+            apiary::http::Response<apiary::response::Body>,
+            apiary::BoxError,
+        > = async move { #call }.await;
+        return resp.map(|mut resp| {
+            resp.extensions_mut().insert(matched);
+            resp
+        });
+    };
+    let call = parse_params_then(parse_params, call);
+
+    let stmt = parse_quote! {
+        if parts.method == apiary::http::Method::#method && #path_matches {
+            #call
+        }
+    };
+    (stmt, layered_method)
+}
+
+fn codegen_query(mode: Mode, name: &syn::Ident, ty: &syn::Type) -> TokenStream {
+    let reject = mode.reject(quote::quote! {
+        apiary::rejection::InvalidQuery {
+            request: apiary::http::Request::from_parts(parts, body),
+            error: std::boxed::Box::new(err),
+        }
+    });
+
+    quote::quote! {
+        let #name: #ty = match apiary::form::from_str(parts.uri.query().unwrap_or("")) {
+            Ok(query) => query,
+            Err(err) => return #reject,
+        };
+    }
 }
 
-fn codegen_handler(handler: &Handler) -> syn::Stmt {
-    syn::parse_quote!(();)
+fn codegen_header(mode: Mode, name: &syn::Ident, ty: &syn::Type, header: &str) -> TokenStream {
+    let reject = mode.reject(quote::quote! {
+        apiary::rejection::InvalidHeader {
+            request: apiary::http::Request::from_parts(parts, body),
+            name: #header,
+            error: err,
+        }
+    });
+
+    // the `Option<T>` header may be missing
+    let (parse, missing) = match wrapped_type(ty, "Option") {
+        Some(inner) => {
+            let parse = from_param(inner, quote::quote!(value));
+            (quote::quote!(#parse.map(Some)), quote::quote!(Ok(None)))
+        }
+        None => (
+            from_param(ty, quote::quote!(value)),
+            quote::quote!(Err("missing header".into())),
+        ),
+    };
+
+    quote::quote! {
+        let value = match apiary::request::header_str(parts.headers.get(#header)) {
+            Ok(Some(value)) => #parse,
+            Ok(None) => #missing,
+            Err(err) => Err(err),
+        };
+        let #name: #ty = match value {
+            Ok(value) => value,
+            Err(err) => return #reject,
+        };
+    }
+}
+
+/// Parses the parameter with the `Param`.
+///
+/// The `Result<T, BoxError>` parameter takes the error of the `T` instead of failing.
+fn from_param(ty: &syn::Type, src: TokenStream) -> TokenStream {
+    match wrapped_type(ty, "Result") {
+        Some(inner) => quote::quote! {
+            std::result::Result::<#ty, apiary::BoxError>::Ok(
+                <#inner as apiary::request::Param>::from_param(#src),
+            )
+        },
+        None => quote::quote!(<#ty as apiary::request::Param>::from_param(#src)),
+    }
+}
+
+/// `T` of the type written like `Option<T>`, for the `wrapper` of `Option`.
+fn wrapped_type<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(ty) if ty.qself.is_none() => ty.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parses the path and host parameters, then runs the `call` with them.
+///
+/// Parameters which fail to parse make this handler not match the request,
+/// but the first failure is reported if no other handler matches either.
+fn parse_params_then(params: Vec<&Param>, call: TokenStream) -> TokenStream {
+    params.into_iter().rev().fold(call, |call, param| {
+        let name = &param.name;
+        let ty = &param.ty;
+        let param_name = name.to_string();
+        let (src, invalid) = match &param.src {
+            ParamSrc::Path { idx } => (
+                quote::quote!(&path[#idx]),
+                quote::quote!(invalid_path_param),
+            ),
+            ParamSrc::Host { idx } => (
+                quote::quote!(&host[#idx]),
+                quote::quote!(invalid_host_param),
+            ),
+            _ => unreachable!("only the path and host parameters are parsed"),
+        };
+
+        let parse = from_param(ty, src);
+        quote::quote! {
+            match #parse {
+                Ok(#name) => {
+                    #call
+                }
+                Err(err) => {
+                    if #invalid.is_none() {
+                        #invalid = Some((#param_name, err));
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Declares the variables keeping the first path and host parameters which failed to parse,
+/// and rejects the request with them, if any handler takes such parameters.
+fn invalid_params(parsed: &Parsed, mode: Mode) -> (TokenStream, TokenStream) {
+    let (invalid_path_param, reject_invalid_path_param) = invalid_param(
+        parsed,
+        mode,
+        |src| matches!(src, ParamSrc::Path { .. }),
+        quote::quote!(invalid_path_param),
+        quote::quote!(InvalidPathParam),
+    );
+    let (invalid_host_param, reject_invalid_host_param) = invalid_param(
+        parsed,
+        mode,
+        |src| matches!(src, ParamSrc::Host { .. }),
+        quote::quote!(invalid_host_param),
+        quote::quote!(InvalidHostParam),
+    );
+
+    (
+        quote::quote! {
+            #invalid_path_param
+            #invalid_host_param
+        },
+        quote::quote! {
+            #reject_invalid_path_param
+            #reject_invalid_host_param
+        },
+    )
+}
+
+fn invalid_param(
+    parsed: &Parsed,
+    mode: Mode,
+    is_src: impl Fn(&ParamSrc) -> bool,
+    var: TokenStream,
+    rejection: TokenStream,
+) -> (TokenStream, TokenStream) {
+    let has_params = parsed
+        .handlers
+        .iter()
+        .any(|handler| handler.params.iter().any(|param| is_src(&param.src)));
+    if !has_params {
+        return (quote::quote!(), quote::quote!());
+    }
+
+    let reject = mode.reject(quote::quote!(apiary::rejection::#rejection { request, name, error }));
+    (
+        quote::quote! {
+            let mut #var: std::option::Option<(&'static str, apiary::BoxError)> =
+                std::option::Option::None;
+        },
+        quote::quote! {
+            if let Some((name, error)) = #var {
+                return #reject;
+            }
+        },
+    )
+}
+
+/// Checks the segment count and the literal segments of the path, but not the parameters.
+///
+/// With the `toggled` the trailing slash should differ from the handler's one,
+/// which is used to find the redirection target.
+fn path_matches(args: &Args, handler: &Handler, toggled: bool) -> TokenStream {
+    let path_len = handler.path.len();
+    let segments = handler
+        .path
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, seg)| Some((idx, seg.as_deref()?)))
+        .map(|(idx, seg)| -> syn::Expr {
+            if args.case_sensitive {
+                parse_quote!(path[#idx] == #seg)
+            } else {
+                parse_quote!(path[#idx].eq_ignore_ascii_case(#seg))
+            }
+        });
+    let trailing_slash = handler.trailing_slash != toggled;
+    let trailing_slash = match args.trailing_slash {
+        TrailingSlash::Ignore => None,
+        TrailingSlash::Strict | TrailingSlash::Redirect => {
+            Some(quote::quote!(&& trailing_slash == #trailing_slash))
+        }
+    };
+
+    let version = handler
+        .version
+        .as_ref()
+        .filter(|_| args.versioning.is_some())
+        .map(|version| quote::quote!(&& version.as_deref() == Some(#version)));
+
+    quote::quote! {
+        (path.len() == #path_len #(&& #segments)* #trailing_slash #version)
+    }
 }
@@ -0,0 +1,151 @@
+use proc_macro2::TokenStream;
+use proc_macro_error::emit_error;
+use syn::parse_quote;
+use syn::spanned::Spanned;
+
+use crate::attr_apiary::parse::{Handler, ParamSrc, Parsed};
+
+#[derive(Debug)]
+pub struct Args {
+    type_name: syn::Ident,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = syn::NestedMeta> + Spanned) -> Option<Args> {
+    let span = args.span();
+    let mut args = args.into_iter();
+
+    let type_name = match (args.next(), args.next()) {
+        (Some(syn::NestedMeta::Meta(syn::Meta::Path(p))), None) => p.get_ident().cloned(),
+        (None, _) => {
+            emit_error!(span, "Missing urls type name");
+            return None;
+        }
+        _ => None,
+    };
+
+    match type_name {
+        Some(type_name) => Some(Args { type_name }),
+        None => {
+            emit_error!(span, "Invalid parameter, requires urls type name");
+            None
+        }
+    }
+}
+
+/// Generates the struct with the URL builder of each handler.
+///
+/// `path_versioning` prefixes the paths of the versioned handlers like `/v2/pets`.
+pub fn codegen(args: Args, parsed: &Parsed, path_versioning: bool) -> Vec<syn::Item> {
+    let vis = &parsed.vis;
+    let type_name = &args.type_name;
+    let doc = format!(
+        "URL builders of the [`{}`] handlers, which percent-encode the path and query parameters.\n\n\
+         They fail if the query parameter isn't serialized as a struct or a map, \
+         a path parameter is empty, `.` or `..`, or the URL is too long.",
+        parsed.trait_name
+    );
+
+    let builders = parsed
+        .handlers
+        .iter()
+        .map(|handler| codegen_builder(handler, path_versioning));
+
+    let type_def: syn::Item = parse_quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy)]
+        #vis struct #type_name;
+    };
+    let impl_urls: syn::Item = parse_quote! {
+        impl #type_name {
+            #(#builders)*
+        }
+    };
+
+    vec![type_def, impl_urls]
+}
+
+fn codegen_builder(handler: &Handler, path_versioning: bool) -> TokenStream {
+    let name = &handler.name;
+    let version = handler.version.as_ref().filter(|_| path_versioning);
+
+    let mut template = String::new();
+    let mut segments: Vec<TokenStream> = vec![];
+    if let Some(version) = version {
+        let prefix = format!("v{}", version);
+        template.push('/');
+        template.push_str(&prefix);
+        segments.push(quote::quote!(std::string::String::from(#prefix)));
+    }
+    for (idx, seg) in handler.path.iter().enumerate() {
+        template.push('/');
+        match seg {
+            Some(seg) => {
+                template.push_str(seg);
+                segments.push(quote::quote!(std::string::String::from(#seg)));
+            }
+            None => {
+                let param = handler
+                    .params
+                    .iter()
+                    .find(|param| matches!(param.src, ParamSrc::Path { idx: i } if i == idx));
+                // the missing parameter is already reported by the parser
+                if let Some(param) = param {
+                    let name = &param.name;
+                    template.push_str(&format!("{{{}}}", name));
+                    segments.push(quote::quote!(std::string::ToString::to_string(&#name)));
+                }
+            }
+        }
+    }
+    if template.is_empty() || handler.trailing_slash {
+        template.push('/');
+    }
+
+    let doc = format!("`{} {}`", handler.http_method.ident(), template);
+    let trailing_slash = handler.trailing_slash;
+
+    let args = handler
+        .params
+        .iter()
+        .filter(|param| matches!(param.src, ParamSrc::Path { .. } | ParamSrc::Query))
+        .map(|param| {
+            let name = &param.name;
+            let ty = &param.ty;
+            quote::quote!(#name: #ty)
+        });
+
+    let queries: Vec<_> = handler
+        .params
+        .iter()
+        .filter(|param| matches!(param.src, ParamSrc::Query))
+        .map(|param| {
+            let name = &param.name;
+            quote::quote!(apiary::form::to_string(&#name)?)
+        })
+        .collect();
+    let query = match &*queries {
+        [] => quote::quote!(None),
+        [query] => quote::quote!(Some(&#query)),
+        queries => quote::quote! {
+            Some(
+                &[#(#queries),*]
+                    .iter()
+                    .filter(|query| !query.is_empty())
+                    .map(|query| &**query)
+                    .collect::<std::vec::Vec<_>>()
+                    .join("&"),
+            )
+        },
+    };
+
+    quote::quote! {
+        #[doc = #doc]
+        pub fn #name(#(#args),*) -> std::result::Result<apiary::http::Uri, apiary::BoxError> {
+            let path = apiary::server::RequestPath {
+                segments: vec![#(#segments),*],
+                trailing_slash: #trailing_slash,
+            };
+            path.to_uri(#query)
+        }
+    }
+}
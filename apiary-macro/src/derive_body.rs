@@ -0,0 +1,76 @@
+use proc_macro2::TokenStream;
+use proc_macro_error::{emit_call_site_error, emit_error};
+use quote::quote;
+
+const BODY: &str = "body";
+const JSON: &str = "json";
+const FORM: &str = "form";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    Json,
+    Form,
+}
+
+pub fn process(input: syn::DeriveInput) -> Option<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let codec = parse_attrs(&input.attrs)?;
+
+    let wrapper = match codec {
+        Codec::Json => quote!(apiary::Json<Self>),
+        Codec::Form => quote!(apiary::Form<Self>),
+    };
+
+    Some(quote! {
+        impl #impl_generics apiary::request::Body for #name #ty_generics #where_clause {
+            const CONTENT_TYPE: &'static str = <#wrapper as apiary::request::Body>::CONTENT_TYPE;
+
+            fn decode(
+                body: apiary::request::BoxBody,
+            ) -> apiary::request::DecodeResult<Self> {
+                let fut = <#wrapper as apiary::request::Body>::decode(body);
+                std::boxed::Box::pin(async move { Ok(fut.await?.0) })
+            }
+
+            fn accepts(content_type: &str) -> bool {
+                <#wrapper as apiary::request::Body>::accepts(content_type)
+            }
+        }
+    })
+}
+
+fn parse_attrs(attrs: &[syn::Attribute]) -> Option<Codec> {
+    let mut codec = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(BODY)) {
+        let list = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) if list.nested.len() == 1 => list,
+            _ => {
+                emit_error!(attr, "Failed to parse attribute, expected #[body(json)]");
+                return None;
+            }
+        };
+
+        let parsed = match list.nested.first().unwrap() {
+            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident(JSON) => Codec::Json,
+            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident(FORM) => Codec::Form,
+            other => {
+                emit_error!(other, "Invalid parameter, expected `json` or `form`");
+                return None;
+            }
+        };
+
+        if codec.is_some() {
+            emit_error!(attr, "Duplicated #[body] attribute");
+            return None;
+        }
+        codec = Some(parsed);
+    }
+
+    if codec.is_none() {
+        emit_call_site_error!("#[derive(Body)] requires the codec like #[body(json)]");
+    }
+
+    codec
+}
@@ -0,0 +1,297 @@
+use proc_macro2::TokenStream;
+use proc_macro_error::emit_error;
+use quote::quote;
+use syn::spanned::Spanned;
+
+const STATUS: &str = "status";
+const JSON: &str = "json";
+const TEXT: &str = "text";
+
+#[derive(Debug, Default)]
+struct Attr {
+    status: Option<u16>,
+    encoding: Encoding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Encoding {
+    /// Delegates to the field's own `Response` impl, or empty body if there's no field.
+    #[default]
+    Inner,
+    /// Serializes the field, or the struct itself, as JSON.
+    Json,
+    /// Uses the `Display` impl of the value.
+    Text,
+}
+
+pub fn process(input: syn::DeriveInput) -> Option<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attr = parse_attrs(&input.attrs)?;
+
+    let arms: Vec<Arm> = match &input.data {
+        syn::Data::Struct(data) => vec![codegen_arm(&attr, quote!(#name), &data.fields, true)?],
+        syn::Data::Enum(data) => {
+            if attr.encoding != Encoding::Inner {
+                emit_error!(
+                    input.ident,
+                    "Body encoding should be specified on each variants"
+                );
+            }
+
+            data.variants
+                .iter()
+                .filter_map(|variant| {
+                    let mut variant_attr = parse_attrs(&variant.attrs)?;
+                    variant_attr.status = variant_attr.status.or(attr.status);
+
+                    let ident = &variant.ident;
+                    let path = quote!(#name::#ident);
+                    codegen_arm(&variant_attr, path, &variant.fields, false)
+                })
+                .collect()
+        }
+        syn::Data::Union(data) => {
+            emit_error!(
+                data.union_token,
+                "#[derive(Response)] doesn't support unions"
+            );
+            return None;
+        }
+    };
+
+    let statuses = arms.iter().map(|arm| &arm.statuses);
+    let into_response = arms.iter().map(|arm| &arm.into_response);
+    let into_response_for = arms.iter().map(|arm| &arm.into_response_for);
+
+    Some(quote! {
+        impl #impl_generics apiary::response::Response for #name #ty_generics #where_clause {
+            fn into_response(
+                self,
+            ) -> std::result::Result<
+                apiary::http::Response<apiary::response::Body>,
+                apiary::BoxError,
+            > {
+                match self {
+                    #(#into_response,)*
+                }
+            }
+
+            fn into_response_for(
+                self,
+                request: &apiary::http::request::Parts,
+            ) -> std::result::Result<
+                apiary::http::Response<apiary::response::Body>,
+                apiary::BoxError,
+            > {
+                match self {
+                    #(#into_response_for,)*
+                }
+            }
+
+            fn statuses() -> std::vec::Vec<apiary::http::StatusCode> {
+                let mut statuses = std::vec::Vec::new();
+                #(
+                    for status in #statuses {
+                        if !statuses.contains(&status) {
+                            statuses.push(status);
+                        }
+                    }
+                )*
+                statuses
+            }
+        }
+    })
+}
+
+fn parse_attrs(attrs: &[syn::Attribute]) -> Option<Attr> {
+    let mut res = Attr::default();
+    let mut found = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(STATUS)) {
+        if found {
+            emit_error!(attr, "Duplicated #[status] attribute");
+            return None;
+        }
+        found = true;
+
+        let list = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list,
+            _ => {
+                emit_error!(attr, "Failed to parse attribute, expected #[status(404)]");
+                return None;
+            }
+        };
+
+        for nested in list.nested {
+            match nested {
+                syn::NestedMeta::Lit(syn::Lit::Int(lit)) if res.status.is_none() => {
+                    let code = lit
+                        .base10_parse::<u16>()
+                        .ok()
+                        .filter(|code| http::StatusCode::from_u16(*code).is_ok());
+                    match code {
+                        Some(code) => res.status = Some(code),
+                        None => {
+                            emit_error!(lit, "Invalid status code");
+                            return None;
+                        }
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(p))
+                    if res.encoding == Encoding::Inner && p.is_ident(JSON) =>
+                {
+                    res.encoding = Encoding::Json;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(p))
+                    if res.encoding == Encoding::Inner && p.is_ident(TEXT) =>
+                {
+                    res.encoding = Encoding::Text;
+                }
+                other => {
+                    emit_error!(other, "Invalid parameter");
+                    return None;
+                }
+            }
+        }
+    }
+
+    Some(res)
+}
+
+fn fields_pattern(path: TokenStream, fields: &syn::Fields, bind: bool) -> TokenStream {
+    match fields {
+        _ if !bind => quote!(#path { .. }),
+        syn::Fields::Unit => path,
+        syn::Fields::Unnamed(fields) => {
+            let names = (0..fields.unnamed.len()).map(|idx| quote::format_ident!("field{}", idx));
+            quote!(#path( #(#names),* ))
+        }
+        syn::Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(#path { #(#names),* })
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Arm {
+    into_response: TokenStream,
+    into_response_for: TokenStream,
+    /// Expression for the possible statuses.
+    statuses: TokenStream,
+}
+
+/// Generates the match arms for both `into_response` and `into_response_for`.
+fn codegen_arm(
+    attr: &Attr,
+    path: TokenStream,
+    fields: &syn::Fields,
+    is_struct: bool,
+) -> Option<Arm> {
+    // `self` is used as a whole for these encodings, so the fields should not be moved out.
+    let bind = !matches!(
+        (attr.encoding, is_struct),
+        (Encoding::Text, _) | (Encoding::Json, true)
+    );
+    let pat = fields_pattern(path, fields, bind);
+
+    let single_field = match fields {
+        syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            let field = fields.unnamed.first().unwrap();
+            Some((quote::format_ident!("field0"), &field.ty))
+        }
+        syn::Fields::Named(fields) if fields.named.len() == 1 => {
+            let field = fields.named.first().unwrap();
+            Some((field.ident.clone().unwrap(), &field.ty))
+        }
+        _ => None,
+    };
+
+    let (resp, resp_for, inner_statuses) = match (attr.encoding, single_field) {
+        (Encoding::Text, _) => {
+            let resp = quote! {
+                apiary::response::Response::into_response(
+                    std::string::ToString::to_string(&self),
+                )?
+            };
+            (resp.clone(), resp, None)
+        }
+        (Encoding::Json, _) if is_struct => {
+            let resp = quote!(apiary::response::Response::into_response(apiary::Json(
+                &self
+            ))?);
+            (resp.clone(), resp, None)
+        }
+        (Encoding::Json, Some((field, _))) => {
+            let resp = quote!(apiary::response::Response::into_response(apiary::Json(#field))?);
+            (resp.clone(), resp, None)
+        }
+        (Encoding::Json, None) => {
+            emit_error!(
+                fields.span(),
+                "`json` encoding requires exactly one field to serialize"
+            );
+            return None;
+        }
+        (Encoding::Inner, None) if fields.is_empty() => {
+            let resp = quote!(apiary::response::Response::into_response(())?);
+            let statuses = quote!(<() as apiary::response::Response>::statuses());
+            (resp.clone(), resp, Some(statuses))
+        }
+        (Encoding::Inner, Some((field, ty))) => (
+            quote!(apiary::response::Response::into_response(#field)?),
+            quote!(apiary::response::Response::into_response_for(#field, request)?),
+            Some(quote!(<#ty as apiary::response::Response>::statuses())),
+        ),
+        (Encoding::Inner, None) => {
+            emit_error!(
+                fields.span(),
+                "Multiple fields require either `json` or `text` encoding"
+            );
+            return None;
+        }
+    };
+
+    Some(match attr.status {
+        Some(code) => {
+            // `406 Not Acceptable` of the failed negotiation is kept, as its body isn't the value
+            let set_status = quote! {
+                if resp.status() != apiary::http::StatusCode::NOT_ACCEPTABLE {
+                    *resp.status_mut() = apiary::http::StatusCode::from_u16(#code)?;
+                }
+            };
+            let not_acceptable = inner_statuses.map(|inner| {
+                quote! {
+                    if #inner.contains(&apiary::http::StatusCode::NOT_ACCEPTABLE) {
+                        statuses.push(apiary::http::StatusCode::NOT_ACCEPTABLE);
+                    }
+                }
+            });
+
+            Arm {
+                into_response: quote! {#pat => {
+                    let mut resp = #resp;
+                    #set_status
+                    Ok(resp)
+                }},
+                into_response_for: quote! {#pat => {
+                    let mut resp = #resp_for;
+                    #set_status
+                    Ok(resp)
+                }},
+                statuses: quote! {{
+                    let mut statuses: std::vec::Vec<_> =
+                        apiary::http::StatusCode::from_u16(#code).into_iter().collect();
+                    #not_acceptable
+                    statuses
+                }},
+            }
+        }
+        None => Arm {
+            into_response: quote!(#pat => Ok(#resp)),
+            into_response_for: quote!(#pat => Ok(#resp_for)),
+            statuses: inner_statuses.unwrap_or_else(|| quote!(vec![apiary::http::StatusCode::OK])),
+        },
+    })
+}
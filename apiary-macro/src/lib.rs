@@ -5,13 +5,41 @@ use proc_macro::TokenStream;
 use proc_macro_error::{abort_if_dirty, proc_macro_error};
 
 mod attr_apiary;
+mod derive_body;
+mod derive_response;
 
 #[proc_macro_attribute]
 #[proc_macro_error]
 pub fn api(args: TokenStream, item: TokenStream) -> TokenStream {
-    let args = syn::parse_macro_input!(args as syn::AttributeArgs);
     let item = syn::parse_macro_input!(item as syn::ItemTrait);
-    let res = attr_apiary::process(args, item);
+    let res = attr_apiary::process(args.into(), item);
+    abort_if_dirty();
+    res.unwrap().into()
+}
+
+/// Implements `apiary::response::Response` for the struct or enum.
+///
+/// `#[status(404)]` on the type or on each variant sets the status code,
+/// except the `406 Not Acceptable` of the inner response which failed the content negotiation.
+/// The body is the single field's own response, or empty for the unit variant.
+/// `#[status(409, json)]` serializes the field as JSON instead,
+/// and `#[status(400, text)]` uses the `Display` impl of the whole value.
+#[proc_macro_derive(Response, attributes(status))]
+#[proc_macro_error]
+pub fn derive_response(item: TokenStream) -> TokenStream {
+    let item = syn::parse_macro_input!(item as syn::DeriveInput);
+    let res = derive_response::process(item);
+    abort_if_dirty();
+    res.unwrap().into()
+}
+
+/// Implements `apiary::request::Body` for the type
+/// with the codec given as `#[body(json)]` or `#[body(form)]`.
+#[proc_macro_derive(Body, attributes(body))]
+#[proc_macro_error]
+pub fn derive_body(item: TokenStream) -> TokenStream {
+    let item = syn::parse_macro_input!(item as syn::DeriveInput);
+    let res = derive_body::process(item);
     abort_if_dirty();
     res.unwrap().into()
 }
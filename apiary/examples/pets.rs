@@ -2,25 +2,51 @@ use apiary::{api, server::Server};
 use async_trait::async_trait;
 use std::sync::Arc;
 
-#[api(server(FooServer))]
+#[api(server(PetsServer))]
 #[async_trait]
-pub trait Foo: Send + Sync + 'static {
-    #[get("/foo/{bar}/{baz}/quux")]
-    async fn bar(self: Arc<Self>, bar: u32, baz: Result<bool, apiary::BoxError>) -> String;
+pub trait Pets: Send + Sync + 'static {
+    #[get("/pets/{id}")]
+    async fn get_pet(self: Arc<Self>, id: u32) -> Result<String, PetError>;
+
+    #[put("/pets/{id}/name")]
+    async fn rename_pet(self: Arc<Self>, id: u32, #[body] name: String) -> Result<(), PetError>;
+}
+
+#[derive(Debug, apiary::Response)]
+pub enum PetError {
+    #[status(404)]
+    NotFound,
+    #[status(409)]
+    Conflict(String),
 }
 
-struct Bar;
+struct Store {
+    names: Vec<&'static str>,
+}
 
 #[async_trait]
-impl Foo for Bar {
-    async fn bar(self: Arc<Self>, bar: u32, baz: Result<bool, apiary::BoxError>) -> String {
-        format!("Hello HTTP! bar: {}, baz: {:?}", bar, baz)
+impl Pets for Store {
+    async fn get_pet(self: Arc<Self>, id: u32) -> Result<String, PetError> {
+        let name = self.names.get(id as usize).ok_or(PetError::NotFound)?;
+        Ok(format!("Pet #{}: {}", id, name))
+    }
+
+    async fn rename_pet(self: Arc<Self>, id: u32, name: String) -> Result<(), PetError> {
+        let prev = self.names.get(id as usize).ok_or(PetError::NotFound)?;
+        Err(PetError::Conflict(format!(
+            "Pet #{} is already named {}, not {}",
+            id, prev, name
+        )))
     }
 }
 
 #[tokio::main]
 async fn main() {
-    FooServer(Arc::new(Bar))
+    let store = Store {
+        names: vec!["Tom", "Jerry"],
+    };
+
+    PetsServer(Arc::new(store))
         .bind("127.0.0.1:9000".parse().unwrap())
         .unwrap()
         .run()
@@ -0,0 +1,458 @@
+//! Cross-Origin Resource Sharing.
+//!
+//! It can be configured per trait with `#[api(cors(...))]`,
+//! or at runtime by wrapping the server into the [`Cors`](Cors).
+//!
+//! ```ignore
+//! #[api(server(PetsServer), cors(origins = ["https://example.com"], credentials, max_age = 600))]
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::{Method, Request, Response, StatusCode};
+use http_body::Body as HttpBody;
+
+use crate::response::{self, CONTENT_TYPE_TEXT};
+use crate::server::{ServeResult, Server};
+use crate::BoxError;
+
+const ANY_ORIGIN_WITH_CREDENTIALS: &str = "CORS credentials can't be allowed for any origin";
+
+/// Server which applies the CORS to every response,
+/// and answers the preflight requests itself.
+#[derive(Debug, Clone)]
+pub struct Cors<S> {
+    server: S,
+    config: CorsConfig,
+}
+
+/// Allowed origins, methods and headers of the CORS.
+///
+/// Nothing is allowed by default, except every methods if none is given.
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Inner {
+    any_origin: bool,
+    origins: Vec<HeaderValue>,
+    methods: Vec<Method>,
+    any_header: bool,
+    headers: Vec<HeaderName>,
+    expose_headers: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl<S: Server> Cors<S> {
+    pub fn new(server: S, config: CorsConfig) -> Self {
+        Cors { server, config }
+    }
+}
+
+impl<S: Server> Server for Cors<S> {
+    fn serve<B>(self, request: Request<B>) -> ServeResult
+    where
+        B: HttpBody + Send + Sync + 'static,
+        B::Error: Into<BoxError>,
+    {
+        let Cors { server, config } = self;
+        config.serve(request, move |request| server.serve(request))
+    }
+}
+
+impl CorsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the origin like `https://example.com`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the origin is not a valid header value.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        let origin = HeaderValue::from_str(origin).expect("invalid CORS origin");
+        self.inner_mut().origins.push(origin);
+        self
+    }
+
+    /// Allows every origin, answered with `*`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the credentials are allowed,
+    /// as it would let any site read the responses with the user's cookies.
+    pub fn allow_any_origin(mut self) -> Self {
+        let inner = self.inner_mut();
+        assert!(!inner.credentials, "{}", ANY_ORIGIN_WITH_CREDENTIALS);
+        inner.any_origin = true;
+        self
+    }
+
+    pub fn allow_method(mut self, method: Method) -> Self {
+        self.inner_mut().methods.push(method);
+        self
+    }
+
+    /// Allows the request header, in addition to the CORS-safelisted ones.
+    pub fn allow_header(mut self, header: HeaderName) -> Self {
+        self.inner_mut().headers.push(header);
+        self
+    }
+
+    pub fn allow_any_header(mut self) -> Self {
+        self.inner_mut().any_header = true;
+        self
+    }
+
+    /// Exposes the response header to the script.
+    pub fn expose_header(mut self, header: HeaderName) -> Self {
+        self.inner_mut().expose_headers.push(header);
+        self
+    }
+
+    /// Allows the credentials like cookies.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any origin is allowed. The origins should be listed instead.
+    pub fn allow_credentials(mut self, credentials: bool) -> Self {
+        let inner = self.inner_mut();
+        assert!(
+            !(credentials && inner.any_origin),
+            "{}",
+            ANY_ORIGIN_WITH_CREDENTIALS
+        );
+        inner.credentials = credentials;
+        self
+    }
+
+    /// How long the client can cache the preflight response.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.inner_mut().max_age = Some(max_age);
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    /// Serves the request with the CORS applied, used by the generated server.
+    ///
+    /// Preflight requests are answered without calling the `serve`.
+    pub fn serve<B, F>(&self, request: Request<B>, serve: F) -> ServeResult
+    where
+        F: FnOnce(Request<B>) -> ServeResult,
+    {
+        let origin = match request.headers().get(header::ORIGIN) {
+            Some(origin) => origin.clone(),
+            // not a CORS request, but still cached apart from the CORS ones
+            None if !self.inner.any_origin => {
+                let fut = serve(request);
+                return Box::pin(async move {
+                    let mut resp = fut.await?;
+                    add_vary_origin(resp.headers_mut());
+                    Ok(resp)
+                });
+            }
+            None => return serve(request),
+        };
+
+        if request.method() == Method::OPTIONS
+            && request
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let resp = self.preflight(&origin, request.headers()).map(|mut resp| {
+                self.vary(resp.headers_mut());
+                resp
+            });
+            return Box::pin(async move { resp });
+        }
+
+        let config = self.clone();
+        let fut = serve(request);
+        Box::pin(async move {
+            let mut resp = fut.await?;
+            config.vary(resp.headers_mut());
+            if config.is_allowed_origin(&origin) {
+                config.apply(&origin, resp.headers_mut());
+            }
+            Ok(resp)
+        })
+    }
+
+    fn is_allowed_origin(&self, origin: &HeaderValue) -> bool {
+        self.inner.any_origin || self.inner.origins.contains(origin)
+    }
+
+    fn preflight(
+        &self,
+        origin: &HeaderValue,
+        request: &HeaderMap,
+    ) -> Result<Response<response::Body>, BoxError> {
+        let inner = &self.inner;
+
+        if !self.is_allowed_origin(origin) {
+            return forbidden("Origin is not allowed");
+        }
+
+        let method = request
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|method| Method::from_bytes(method.as_bytes()).ok());
+        let method = match method {
+            Some(method) if inner.methods.is_empty() || inner.methods.contains(&method) => method,
+            _ => return forbidden("Method is not allowed"),
+        };
+
+        let headers: Vec<_> = request
+            .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if !inner.any_header {
+            let allowed = headers.iter().all(|name| {
+                inner
+                    .headers
+                    .iter()
+                    .any(|allowed| allowed.as_str().eq_ignore_ascii_case(name))
+            });
+            if !allowed {
+                return forbidden("Header is not allowed");
+            }
+        }
+
+        let mut resp = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(response::Body::empty())?;
+        let resp_headers = resp.headers_mut();
+        self.apply(origin, resp_headers);
+
+        let methods = if inner.methods.is_empty() {
+            method.as_str().to_owned()
+        } else {
+            join(inner.methods.iter().map(Method::as_str))
+        };
+        resp_headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_str(&methods)?,
+        );
+        if !headers.is_empty() {
+            let allow_headers = if inner.any_header {
+                headers.join(", ")
+            } else {
+                join(inner.headers.iter().map(HeaderName::as_str))
+            };
+            resp_headers.insert(
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                HeaderValue::from_str(&allow_headers)?,
+            );
+        }
+        if let Some(max_age) = inner.max_age {
+            resp_headers.insert(
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(max_age.as_secs()),
+            );
+        }
+
+        Ok(resp)
+    }
+
+    /// Adds the headers shared by the preflight and the actual response.
+    fn apply(&self, origin: &HeaderValue, headers: &mut HeaderMap) {
+        let inner = &self.inner;
+
+        if inner.any_origin {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+        } else {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        }
+        if inner.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        if !inner.expose_headers.is_empty() {
+            let expose = join(inner.expose_headers.iter().map(HeaderName::as_str));
+            if let Ok(expose) = HeaderValue::from_str(&expose) {
+                headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+            }
+        }
+    }
+
+    /// Marks the response as differing by the origin, unless every origin is allowed.
+    ///
+    /// It's added even if the origin is not allowed,
+    /// so the caches don't serve the response without the CORS headers to the allowed ones.
+    fn vary(&self, headers: &mut HeaderMap) {
+        if !self.inner.any_origin {
+            add_vary_origin(headers);
+        }
+    }
+}
+
+fn add_vary_origin(headers: &mut HeaderMap) {
+    let has_vary = headers
+        .get_all(header::VARY)
+        .iter()
+        .any(|vary| vary.as_bytes().eq_ignore_ascii_case(b"origin"));
+    if !has_vary {
+        headers.append(header::VARY, HeaderValue::from_static("origin"));
+    }
+}
+
+fn join<'a>(items: impl Iterator<Item = &'a str>) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
+
+fn forbidden(reason: &str) -> Result<Response<response::Body>, BoxError> {
+    let resp = format!("403 Forbidden - CORS preflight failed: {}", reason);
+
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+        .header(header::CONTENT_LENGTH, resp.len())
+        .body(response::Body::once(resp))
+        .map_err(|err| Box::new(err) as _)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CorsConfig {
+        CorsConfig::new()
+            .allow_origin("https://example.com")
+            .allow_method(Method::GET)
+            .allow_method(Method::PUT)
+            .allow_header(HeaderName::from_static("x-token"))
+            .expose_header(HeaderName::from_static("x-total"))
+            .max_age(Duration::from_secs(600))
+    }
+
+    async fn call(
+        config: &CorsConfig,
+        request: http::request::Builder,
+    ) -> (Response<response::Body>, bool) {
+        let mut called = false;
+        let resp = config
+            .serve(request.body(()).unwrap(), |_| {
+                called = true;
+                Box::pin(async { Ok(Response::new(response::Body::empty())) })
+            })
+            .await
+            .unwrap();
+        (resp, called)
+    }
+
+    fn preflight(origin: &str, method: &str) -> http::request::Builder {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/pets")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, method)
+    }
+
+    #[tokio::test]
+    async fn preflight_allowed() {
+        let request = preflight("https://example.com", "PUT")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "X-Token");
+        let (resp, called) = call(&config(), request).await;
+        assert!(!called);
+
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, PUT");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "x-token");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[header::VARY], "origin");
+    }
+
+    #[tokio::test]
+    async fn preflight_forbidden() {
+        let requests = vec![
+            preflight("https://evil.com", "GET"),
+            preflight("https://example.com", "DELETE"),
+            preflight("https://example.com", "GET")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-token, x-other"),
+        ];
+        for request in requests {
+            let (resp, called) = call(&config(), request).await;
+            assert!(!called);
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+            assert!(!resp
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+            assert_eq!(resp.headers()[header::VARY], "origin");
+        }
+    }
+
+    #[tokio::test]
+    async fn actual_request() {
+        let request = Request::get("/pets").header(header::ORIGIN, "https://example.com");
+        let (resp, called) = call(&config(), request).await;
+        assert!(called);
+        assert_eq!(
+            resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+        assert_eq!(
+            resp.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "x-total"
+        );
+
+        assert_eq!(resp.headers()[header::VARY], "origin");
+
+        // not a CORS request
+        let (resp, called) = call(&config(), Request::get("/pets")).await;
+        assert!(called);
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(resp.headers()[header::VARY], "origin");
+
+        let request = Request::get("/pets").header(header::ORIGIN, "https://evil.com");
+        let (resp, _) = call(&config(), request).await;
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(resp.headers()[header::VARY], "origin");
+    }
+
+    #[tokio::test]
+    async fn any_origin() {
+        let request = || Request::get("/").header(header::ORIGIN, "https://a.com");
+
+        let (resp, _) = call(&CorsConfig::new().allow_any_origin(), request()).await;
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!resp.headers().contains_key(header::VARY));
+    }
+
+    #[test]
+    #[should_panic]
+    fn any_origin_with_credentials() {
+        let _ = CorsConfig::new().allow_credentials(true).allow_any_origin();
+    }
+
+    #[test]
+    #[should_panic]
+    fn credentials_with_any_origin() {
+        let _ = CorsConfig::new().allow_any_origin().allow_credentials(true);
+    }
+}
@@ -0,0 +1,180 @@
+//! `application/x-www-form-urlencoded` encoding.
+//!
+//! Nested keys like `pet[name]=Tom` are decoded as the nested struct or map,
+//! and repeated keys like `tag=a&tag=b` or `tag[]=a&tag[]=b` as the sequence.
+//! The same decoder is used for both the request body and the query string.
+
+use http::{header, Response as HttpResponse, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::request::{self, BoxBody, DecodeResult};
+use crate::response::{self, Response};
+use crate::BoxError;
+
+mod de;
+mod ser;
+
+pub use de::Error;
+
+pub(crate) const CONTENT_TYPE_FORM: &str = "application/x-www-form-urlencoded";
+
+/// URL encoded form body.
+///
+/// It can be used as both the request body and the response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Form<T>(pub T);
+
+/// Decodes the value from the URL encoded string, like the query string.
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T, Error> {
+    from_bytes(input.as_bytes())
+}
+
+/// Decodes the value from the URL encoded bytes.
+pub fn from_bytes<T: DeserializeOwned>(input: &[u8]) -> Result<T, Error> {
+    T::deserialize(de::Value::parse(input)?)
+}
+
+/// Encodes the value as the URL encoded string.
+///
+/// The value should be serialized as a struct or a map.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    ser::to_string(value)
+}
+
+impl<T> request::Body for Form<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    const CONTENT_TYPE: &'static str = CONTENT_TYPE_FORM;
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(async move {
+            let bytes = request::to_bytes(body).await?;
+            Ok(Form(from_bytes(&bytes)?))
+        })
+    }
+
+    fn accepts(content_type: &str) -> bool {
+        request::media_type(content_type).eq_ignore_ascii_case(CONTENT_TYPE_FORM)
+    }
+}
+
+impl<T: Serialize> Response for Form<T> {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
+    fn into_response(self) -> Result<HttpResponse<response::Body>, BoxError> {
+        let buf = to_string(&self.0)?;
+
+        HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_FORM)
+            .header(header::CONTENT_LENGTH, buf.len())
+            .body(response::Body::once(buf))
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Pet {
+        name: String,
+        age: u32,
+        tags: Vec<String>,
+        owner: Option<Owner>,
+        kind: Kind,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Owner {
+        name: String,
+        admin: bool,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        Cat,
+        Dog,
+    }
+
+    fn pet() -> Pet {
+        Pet {
+            name: "Tom & Jerry".into(),
+            age: 3,
+            tags: vec!["a b".into(), "c".into()],
+            owner: Some(Owner {
+                name: "ü".into(),
+                admin: true,
+            }),
+            kind: Kind::Cat,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let encoded = to_string(&pet()).unwrap();
+        assert_eq!(
+            encoded,
+            "age=3&kind=cat&name=Tom+%26+Jerry\
+             &owner%5Badmin%5D=true&owner%5Bname%5D=%C3%BC&tags=a+b&tags=c"
+        );
+        assert_eq!(from_str::<Pet>(&encoded).unwrap(), pet());
+    }
+
+    #[test]
+    fn nested_keys() {
+        let decoded: Pet =
+            from_str("name=Tom&age=3&tags[]=a&tags[]=b&owner[name]=x&owner[admin]=false&kind=dog")
+                .unwrap();
+        assert_eq!(decoded.tags, ["a", "b"]);
+        assert_eq!(
+            decoded.owner,
+            Some(Owner {
+                name: "x".into(),
+                admin: false,
+            })
+        );
+        assert_eq!(decoded.kind, Kind::Dog);
+
+        let indexed: BTreeMap<String, Vec<u32>> = from_str("seq[1]=20&seq[0]=10").unwrap();
+        assert_eq!(indexed["seq"], [10, 20]);
+    }
+
+    #[test]
+    fn empty_value_is_none() {
+        let decoded: BTreeMap<String, Option<u32>> = from_str("a=&b=1").unwrap();
+        assert_eq!(decoded["a"], None);
+        assert_eq!(decoded["b"], Some(1));
+    }
+
+    #[test]
+    fn invalid_form() {
+        assert!(from_str::<Pet>("name=Tom&age=x").is_err());
+        assert!(from_str::<BTreeMap<String, String>>("a[b=1").is_err());
+        assert!(from_str::<BTreeMap<String, String>>("a=1&a[b]=2").is_err());
+        assert!(to_string(&[1, 2]).is_err());
+        assert_eq!(to_string(&()).unwrap(), "");
+    }
+
+    #[test]
+    fn deep_keys() {
+        type Nested = BTreeMap<String, BTreeMap<String, String>>;
+
+        let deep = format!("a{}=1", "[x]".repeat(100_000));
+        assert!(from_str::<Nested>(&deep).is_err());
+
+        let repeated: Vec<_> = (0..10_000).map(|i| format!("a[k{}]=v", i)).collect();
+        let decoded: Nested = from_str(&repeated.join("&")).unwrap();
+        assert_eq!(decoded["a"].len(), 10_000);
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{self, IntoDeserializer, Visitor};
+
+/// Maximum number of the nested keys like `a[b][c]`,
+/// which limits the recursion of the decoding.
+const MAX_DEPTH: usize = 32;
+
+/// Failed to encode or decode the URL encoded form.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Error(String);
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Decoded form as a tree of the nested keys.
+#[derive(Debug)]
+pub enum Value {
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Map),
+}
+
+/// Nested keys in the order they first appear, indexed to find the repeated ones.
+#[derive(Debug, Default)]
+pub struct Map {
+    entries: Vec<(String, Value)>,
+    index: HashMap<String, usize>,
+}
+
+impl Value {
+    pub fn parse(input: &[u8]) -> Result<Self, Error> {
+        let mut root = Map::default();
+
+        for (key, value) in form_urlencoded::parse(input) {
+            let path = split_key(&key)?;
+            insert(&mut root, &path, value.into_owned())?;
+        }
+
+        Ok(Value::Map(root))
+    }
+
+    fn into_str(self) -> Result<String, Error> {
+        match self {
+            Value::Str(s) => Ok(s),
+            Value::Seq(_) => Err(de::Error::custom("expected a single value, found repeated")),
+            Value::Map(_) => Err(de::Error::custom("expected a single value, found nested")),
+        }
+    }
+
+    fn into_seq(self) -> Result<Vec<Value>, Error> {
+        match self {
+            Value::Str(s) => Ok(vec![Value::Str(s)]),
+            Value::Seq(seq) => Ok(seq),
+            // `seq[0]=a&seq[1]=b`
+            Value::Map(map) => {
+                let mut indexed = map
+                    .entries
+                    .into_iter()
+                    .map(|(key, value)| match key.parse::<usize>() {
+                        Ok(idx) => Ok((idx, value)),
+                        Err(_) => Err(de::Error::custom(format_args!(
+                            "expected a sequence index, found `{}`",
+                            key
+                        ))),
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                indexed.sort_by_key(|(idx, _)| *idx);
+                Ok(indexed.into_iter().map(|(_, value)| value).collect())
+            }
+        }
+    }
+}
+
+/// Splits `a[b][]` into `["a", "b", ""]`.
+fn split_key(key: &str) -> Result<Vec<&str>, Error> {
+    let (head, mut rest) = match key.find('[') {
+        Some(idx) => key.split_at(idx),
+        None => return Ok(vec![key]),
+    };
+    let mut path = vec![head];
+
+    while !rest.is_empty() {
+        let end = match (rest.strip_prefix('['), rest.find(']')) {
+            (Some(_), Some(end)) => end,
+            _ => {
+                return Err(de::Error::custom(format_args!(
+                    "invalid nested key `{}`",
+                    key
+                )))
+            }
+        };
+        path.push(&rest[1..end]);
+        rest = &rest[end + 1..];
+
+        if path.len() > MAX_DEPTH {
+            return Err(de::Error::custom(format_args!(
+                "nested key is deeper than {} levels",
+                MAX_DEPTH
+            )));
+        }
+    }
+
+    Ok(path)
+}
+
+impl Map {
+    fn push(&mut self, key: &str, value: Value) -> usize {
+        let idx = self.entries.len();
+        self.entries.push((key.to_owned(), value));
+        self.index.insert(key.to_owned(), idx);
+        idx
+    }
+}
+
+fn insert(map: &mut Map, path: &[&str], value: String) -> Result<(), Error> {
+    let (key, rest) = match path.split_first() {
+        Some((key, rest)) => (*key, rest),
+        None => return Ok(()),
+    };
+    let idx = match map.index.get(key) {
+        Some(&idx) => idx,
+        None => {
+            let node = match rest.first() {
+                None => {
+                    map.push(key, Value::Str(value));
+                    return Ok(());
+                }
+                Some(&"") => Value::Seq(vec![]),
+                Some(_) => Value::Map(Map::default()),
+            };
+            map.push(key, node)
+        }
+    };
+    let node = &mut map.entries[idx].1;
+
+    match (node, rest.split_first()) {
+        // `key=a&key=b`
+        (node @ Value::Str(_), None) => {
+            let prev = std::mem::replace(node, Value::Seq(vec![]));
+            *node = Value::Seq(vec![prev, Value::Str(value)]);
+            Ok(())
+        }
+        (Value::Seq(seq), None) => {
+            seq.push(Value::Str(value));
+            Ok(())
+        }
+        // `key[]=a&key[]=b`
+        (Value::Seq(seq), Some((&"", &[]))) => {
+            seq.push(Value::Str(value));
+            Ok(())
+        }
+        (Value::Seq(seq), Some((&"", rest))) => {
+            let mut elem = Map::default();
+            insert(&mut elem, rest, value)?;
+            seq.push(Value::Map(elem));
+            Ok(())
+        }
+        (Value::Map(map), Some(_)) => insert(map, rest, value),
+        _ => Err(de::Error::custom(format_args!(
+            "conflicting values for the key `{}`",
+            key
+        ))),
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parse {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let s = self.into_str()?;
+            visitor.$visit(s.parse().map_err(de::Error::custom)?)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_string(s),
+            Value::Seq(seq) => visit_seq(seq, visitor),
+            Value::Map(map) => visit_map(map, visitor),
+        }
+    }
+
+    deserialize_parse! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.into_str()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.into_str()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.into_str()?.into_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.into_str()?.into_bytes())
+    }
+
+    /// Empty value like `key=` is decoded as `None`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) if s.is_empty() => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visit_seq(self.into_seq()?, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Map(map) => visit_map(map, visitor),
+            _ => Err(de::Error::custom("expected nested keys")),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::Str(s) => visitor.visit_enum(s.into_deserializer()),
+            Value::Map(map) if map.entries.len() == 1 => visitor.visit_enum(
+                MapAccessDeserializer::new(MapDeserializer::new(map.entries.into_iter())),
+            ),
+            _ => Err(de::Error::custom("expected an enum variant")),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(seq: Vec<Value>, visitor: V) -> Result<V::Value, Error> {
+    let mut seq = SeqDeserializer::new(seq.into_iter());
+    let res = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(res)
+}
+
+fn visit_map<'de, V: Visitor<'de>>(map: Map, visitor: V) -> Result<V::Value, Error> {
+    let mut map = MapDeserializer::new(map.entries.into_iter());
+    let res = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(res)
+}
@@ -0,0 +1,62 @@
+use serde::ser::Error as _;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Error;
+
+type Serializer = form_urlencoded::Serializer<'static, String>;
+
+/// Encodes the value through the `serde_json::Value`, using the nested keys
+/// for the nested structs and the repeated keys for the sequences.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let map = match serde_json::to_value(value).map_err(Error::custom)? {
+        Value::Object(map) => map,
+        Value::Null => return Ok(String::new()),
+        _ => {
+            return Err(Error::custom(
+                "form should be encoded from a struct or a map",
+            ))
+        }
+    };
+
+    let mut ser = Serializer::new(String::new());
+    for (key, value) in map {
+        append(&mut ser, key, value);
+    }
+
+    Ok(ser.finish())
+}
+
+fn append(ser: &mut Serializer, key: String, value: Value) {
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => {
+            ser.append_pair(&key, if b { "true" } else { "false" });
+        }
+        Value::Number(n) => {
+            ser.append_pair(&key, &n.to_string());
+        }
+        Value::String(s) => {
+            ser.append_pair(&key, &s);
+        }
+        Value::Array(seq) if seq.iter().all(is_scalar) => {
+            for value in seq {
+                append(ser, key.clone(), value);
+            }
+        }
+        Value::Array(seq) => {
+            for (idx, value) in seq.into_iter().enumerate() {
+                append(ser, format!("{}[{}]", key, idx), value);
+            }
+        }
+        Value::Object(map) => {
+            for (field, value) in map {
+                append(ser, format!("{}[{}]", key, field), value);
+            }
+        }
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
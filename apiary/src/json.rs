@@ -0,0 +1,126 @@
+use http::{header, Response as HttpResponse, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::request::{self, BoxBody, DecodeResult};
+use crate::response::{self, Response};
+use crate::BoxError;
+
+pub(crate) const CONTENT_TYPE_JSON: &str = "application/json";
+
+/// JSON encoded body.
+///
+/// It can be used as both the request body and the response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Json<T>(pub T);
+
+impl<T> request::Body for Json<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    const CONTENT_TYPE: &'static str = CONTENT_TYPE_JSON;
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(async move {
+            let bytes = request::to_bytes(body).await?;
+            Ok(Json(serde_json::from_slice(&bytes)?))
+        })
+    }
+
+    /// Accepts `application/json` and the `+json` suffixed types like `application/problem+json`.
+    fn accepts(content_type: &str) -> bool {
+        let media_type = request::media_type(content_type).to_ascii_lowercase();
+        media_type == CONTENT_TYPE_JSON || media_type.ends_with("+json")
+    }
+}
+
+impl<T: Serialize> Response for Json<T> {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
+    fn into_response(self) -> Result<HttpResponse<response::Body>, BoxError> {
+        let buf = serde_json::to_vec(&self.0)?;
+
+        HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_JSON)
+            .header(header::CONTENT_LENGTH, buf.len())
+            .body(response::Body::once(buf))
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http_body::{Body as _, Full};
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::request::Body as _;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Pet {
+        name: String,
+        age: u32,
+    }
+
+    fn body(data: &'static str) -> BoxBody {
+        request::boxed(Full::new(Bytes::from(data)))
+    }
+
+    #[tokio::test]
+    async fn decode() {
+        let pet = Json::<Pet>::decode(body(r#"{"name":"Tom","age":3}"#))
+            .await
+            .unwrap();
+        assert_eq!(
+            pet.0,
+            Pet {
+                name: "Tom".into(),
+                age: 3
+            }
+        );
+
+        assert!(Json::<Pet>::decode(body(r#"{"name":"Tom""#)).await.is_err());
+        assert!(Json::<Pet>::decode(body(r#"{"name":"Tom"}"#))
+            .await
+            .is_err());
+        assert!(Json::<Pet>::decode(body("")).await.is_err());
+    }
+
+    #[test]
+    fn accepts() {
+        assert!(Json::<Pet>::accepts("application/json"));
+        assert!(Json::<Pet>::accepts("Application/JSON; charset=utf-8"));
+        assert!(Json::<Pet>::accepts("application/problem+json"));
+        assert!(!Json::<Pet>::accepts("text/plain"));
+        assert!(!Json::<Pet>::accepts("application/jsonl"));
+    }
+
+    #[tokio::test]
+    async fn response() {
+        let resp = Json(Pet {
+            name: "Tom".into(),
+            age: 3,
+        })
+        .into_response()
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], CONTENT_TYPE_JSON);
+
+        let expected = r#"{"name":"Tom","age":3}"#;
+        assert_eq!(
+            resp.headers()[header::CONTENT_LENGTH],
+            expected.len().to_string()
+        );
+
+        let mut body = resp.into_body();
+        let mut buf = vec![];
+        while let Some(data) = body.data().await {
+            buf.extend_from_slice(&data.unwrap());
+        }
+        assert_eq!(buf, expected.as_bytes());
+    }
+}
@@ -1,12 +1,36 @@
+pub mod cors;
+#[cfg(feature = "serde")]
+pub mod form;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "multipart")]
+pub mod multipart;
+#[cfg(feature = "serde")]
+mod negotiated;
+pub mod rejection;
 pub mod request;
 pub mod response;
+pub mod route;
+pub mod router;
 pub mod server;
+#[cfg(feature = "ws")]
+pub mod ws;
 
+#[cfg(feature = "serde")]
+pub use form::Form;
+#[cfg(feature = "serde")]
+pub use json::Json;
+#[cfg(feature = "multipart")]
+pub use multipart::Multipart;
+#[cfg(feature = "serde")]
+pub use negotiated::Negotiated;
+pub use route::{MatchedRoute, RouteInfo};
+pub use router::Router;
 pub use server::Server;
 
 pub use {http, http_body, tower};
 
 #[cfg(feature = "macro")]
-pub use apiary_macro::api;
+pub use apiary_macro::{api, Response};
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
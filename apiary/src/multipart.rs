@@ -0,0 +1,241 @@
+//! `multipart/form-data` request body.
+//!
+//! Parts are streamed as they arrive, so large file uploads don't need to be
+//! buffered in memory. Text fields can be buffered with [`Part::text`](Part::text),
+//! and file parts can be spooled to a temporary file with [`Part::spool`](Part::spool).
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use http::request::Parts;
+use http::{header, HeaderMap};
+use http_body::Body as HttpBody;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+
+use crate::request::{self, BoxBody, DecodeResult};
+use crate::BoxError;
+
+/// Streaming `multipart/form-data` body.
+#[derive(Debug)]
+pub struct Multipart {
+    inner: multer::Multipart<'static>,
+}
+
+/// Single part of the [`Multipart`](Multipart) body.
+///
+/// Parts should be consumed in order, the next part is available after this one is read or dropped.
+#[derive(Debug)]
+pub struct Part {
+    inner: multer::Field<'static>,
+}
+
+/// Part stored in memory, or in a temporary file once it's larger than the limit.
+#[derive(Debug)]
+pub enum Spooled {
+    Memory(Bytes),
+    File(NamedTempFile),
+}
+
+impl Multipart {
+    /// Waits for the next part, returns `None` if there's no more parts.
+    pub async fn next_part(&mut self) -> Result<Option<Part>, BoxError> {
+        let inner = self.inner.next_field().await?;
+        Ok(inner.map(|inner| Part { inner }))
+    }
+}
+
+impl request::Body for Multipart {
+    const CONTENT_TYPE: &'static str = "multipart/form-data";
+
+    fn decode(_body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(async { Err("Multipart body requires the boundary from the request".into()) })
+    }
+
+    fn decode_request(request: &Parts, body: BoxBody) -> DecodeResult<Self> {
+        let boundary = request
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .ok_or_else(|| BoxError::from("Missing Content-Type header for the multipart body"))
+            .and_then(|content_type| Ok(multer::parse_boundary(content_type)?));
+
+        Box::pin(async move {
+            Ok(Multipart {
+                inner: multer::Multipart::new(BodyStream(body), boundary?),
+            })
+        })
+    }
+
+    fn accepts(content_type: &str) -> bool {
+        request::media_type(content_type).eq_ignore_ascii_case(Self::CONTENT_TYPE)
+    }
+}
+
+impl Part {
+    /// Name of the form field this part is for.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    /// File name of the uploaded file, if this part is a file.
+    pub fn filename(&self) -> Option<&str> {
+        self.inner.file_name()
+    }
+
+    /// Value of the part's `Content-Type` header.
+    pub fn content_type(&self) -> Option<&str> {
+        self.inner.content_type().map(|mime| mime.as_ref())
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// Waits for the next chunk of this part, returns `None` if the part is ended.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, BoxError> {
+        Ok(self.inner.chunk().await?)
+    }
+
+    /// Buffers the whole part in memory.
+    pub async fn bytes(self) -> Result<Bytes, BoxError> {
+        Ok(self.inner.bytes().await?)
+    }
+
+    /// Buffers the whole part in memory as a text.
+    pub async fn text(self) -> Result<String, BoxError> {
+        Ok(self.inner.text().await?)
+    }
+
+    /// Buffers the part in memory until it exceeds `limit` bytes,
+    /// and spools it to a temporary file after that.
+    pub async fn spool(mut self, limit: usize) -> Result<Spooled, BoxError> {
+        let mut buf = BytesMut::new();
+
+        while let Some(chunk) = self.chunk().await? {
+            if buf.len() + chunk.len() <= limit {
+                buf.extend_from_slice(&chunk);
+                continue;
+            }
+
+            let temp = tokio::task::spawn_blocking(NamedTempFile::new).await??;
+            let mut file = tokio::fs::File::from_std(temp.reopen()?);
+            file.write_all(&buf).await?;
+            file.write_all(&chunk).await?;
+
+            while let Some(chunk) = self.chunk().await? {
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+
+            return Ok(Spooled::File(temp));
+        }
+
+        Ok(Spooled::Memory(buf.freeze()))
+    }
+}
+
+/// Adapts the request body into the byte stream the `multer` expects.
+struct BodyStream(BoxBody);
+
+impl Stream for BodyStream {
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_data(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Request;
+    use http_body::Full;
+
+    use super::*;
+    use crate::request::Body as _;
+
+    const BODY: &str = "--XYZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        My cat\r\n\
+        --XYZ\r\n\
+        Content-Disposition: form-data; name=\"avatar\"; filename=\"cat.png\"\r\n\
+        Content-Type: image/png\r\n\
+        \r\n\
+        0123456789\r\n\
+        --XYZ--\r\n";
+
+    async fn decode(content_type: &str, body: &'static str) -> Result<Multipart, BoxError> {
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(())
+            .unwrap();
+        let body = request::boxed(Full::new(Bytes::from(body)));
+        Multipart::decode_request(&request.into_parts().0, body).await
+    }
+
+    #[tokio::test]
+    async fn parts() {
+        let mut multipart = decode("multipart/form-data; boundary=XYZ", BODY)
+            .await
+            .unwrap();
+
+        let title = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(title.name(), Some("title"));
+        assert_eq!(title.filename(), None);
+        assert_eq!(title.text().await.unwrap(), "My cat");
+
+        let avatar = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(avatar.name(), Some("avatar"));
+        assert_eq!(avatar.filename(), Some("cat.png"));
+        assert_eq!(avatar.content_type(), Some("image/png"));
+        assert_eq!(avatar.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(avatar.bytes().await.unwrap(), "0123456789");
+
+        assert!(multipart.next_part().await.unwrap().is_none());
+    }
+
+    async fn spool(limit: usize) -> Spooled {
+        let mut multipart = decode("multipart/form-data; boundary=XYZ", BODY)
+            .await
+            .unwrap();
+        multipart.next_part().await.unwrap().unwrap();
+        let avatar = multipart.next_part().await.unwrap().unwrap();
+        avatar.spool(limit).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn spool_over_limit() {
+        match spool(10).await {
+            Spooled::Memory(bytes) => assert_eq!(bytes, "0123456789"),
+            Spooled::File(_) => panic!("part within the limit is spooled to a file"),
+        }
+
+        match spool(4).await {
+            Spooled::File(file) => {
+                assert_eq!(std::fs::read(file.path()).unwrap(), b"0123456789");
+            }
+            Spooled::Memory(_) => panic!("part over the limit is kept in memory"),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_boundary() {
+        assert!(!Multipart::accepts("application/json"));
+        assert!(Multipart::accepts("Multipart/Form-Data; boundary=XYZ"));
+
+        assert!(decode("application/json", BODY).await.is_err());
+        assert!(decode("multipart/form-data", BODY).await.is_err());
+
+        let mut multipart = decode("multipart/form-data; boundary=ABC", BODY)
+            .await
+            .unwrap();
+        assert!(multipart.next_part().await.is_err());
+    }
+}
@@ -0,0 +1,365 @@
+use http::request::Parts;
+use http::{header, HeaderValue, Response as HttpResponse, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::json::CONTENT_TYPE_JSON;
+use crate::request::{self, BoxBody, DecodeResult};
+use crate::response::{self, Response, CONTENT_TYPE_TEXT};
+use crate::BoxError;
+
+/// Body encoded with the codec negotiated from the request headers.
+///
+/// As a response it picks the codec from the `Accept` request header,
+/// and answers `406 Not Acceptable` if none of them is acceptable.
+/// As a request body it picks the codec from the `Content-Type` request header.
+///
+/// JSON is always available and used by default.
+/// MessagePack and CBOR are available with the `msgpack` and `cbor` features.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Negotiated<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    Json,
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+/// Available codecs, in the order of the server preference.
+const CODECS: &[Codec] = &[
+    Codec::Json,
+    #[cfg(feature = "msgpack")]
+    Codec::MsgPack,
+    #[cfg(feature = "cbor")]
+    Codec::Cbor,
+];
+
+impl Codec {
+    fn content_type(self) -> &'static str {
+        match self {
+            Codec::Json => CONTENT_TYPE_JSON,
+            #[cfg(feature = "msgpack")]
+            Codec::MsgPack => "application/msgpack",
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => "application/cbor",
+        }
+    }
+
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        let essence = request::media_type(content_type);
+
+        CODECS.iter().copied().find(|codec| codec.matches(essence))
+    }
+
+    /// Checks the media type without parameters, like `application/json`.
+    fn matches(self, essence: &str) -> bool {
+        essence.eq_ignore_ascii_case(self.content_type())
+            || self
+                .aliases()
+                .iter()
+                .any(|alias| essence.eq_ignore_ascii_case(alias))
+            || self
+                .suffix()
+                .is_some_and(|suffix| essence.to_ascii_lowercase().ends_with(suffix))
+    }
+
+    fn aliases(self) -> &'static [&'static str] {
+        match self {
+            Codec::Json => &[],
+            #[cfg(feature = "msgpack")]
+            Codec::MsgPack => &["application/x-msgpack", "application/vnd.msgpack"],
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => &[],
+        }
+    }
+
+    /// Structured syntax suffix like the `+json` of the `application/problem+json`.
+    fn suffix(self) -> Option<&'static str> {
+        match self {
+            Codec::Json => Some("+json"),
+            #[cfg(feature = "msgpack")]
+            Codec::MsgPack => None,
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => Some("+cbor"),
+        }
+    }
+
+    /// Picks the codec with the highest quality from the `Accept` header,
+    /// with the media type to respond.
+    ///
+    /// The media types with the suffix like `application/problem+json` are responded as is.
+    fn from_accept(accept: &str) -> Option<(Self, String)> {
+        let mut best: Option<(Codec, String, f32)> = None;
+
+        for &codec in CODECS {
+            let suffixed = accept.split(',').map(request::media_type).filter(|media| {
+                codec
+                    .suffix()
+                    .is_some_and(|suffix| media.to_ascii_lowercase().ends_with(suffix))
+                    && !media.starts_with('*')
+            });
+            let candidates = std::iter::once(codec.content_type()).chain(suffixed);
+
+            for media in candidates {
+                let q = quality(accept, media);
+                if q > 0.0 && best.as_ref().is_none_or(|(_, _, best_q)| q > *best_q) {
+                    best = Some((codec, media.to_owned(), q));
+                }
+            }
+        }
+
+        best.map(|(codec, media, _)| (codec, media))
+    }
+
+    fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, BoxError> {
+        match self {
+            Codec::Json => Ok(serde_json::to_vec(value)?),
+            #[cfg(feature = "msgpack")]
+            Codec::MsgPack => Ok(rmp_serde::to_vec_named(value)?),
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => {
+                let mut buf = vec![];
+                ciborium::ser::into_writer(value, &mut buf)?;
+                Ok(buf)
+            }
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, BoxError> {
+        match self {
+            Codec::Json => Ok(serde_json::from_slice(bytes)?),
+            #[cfg(feature = "msgpack")]
+            Codec::MsgPack => Ok(rmp_serde::from_slice(bytes)?),
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => Ok(ciborium::de::from_reader(bytes)?),
+        }
+    }
+}
+
+/// Quality of the media type from the most specific matching range of the `Accept` header.
+fn quality(accept: &str, content_type: &str) -> f32 {
+    let (ty, subtype) = content_type.split_once('/').unwrap_or((content_type, ""));
+    let mut best: Option<(u8, f32)> = None;
+
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let media = params.next().unwrap_or("").trim();
+        let (range_ty, range_subtype) = match media.split_once('/') {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        let specificity = if range_ty == "*" && range_subtype == "*" {
+            0
+        } else if range_ty.eq_ignore_ascii_case(ty) && range_subtype == "*" {
+            1
+        } else if range_ty.eq_ignore_ascii_case(ty) && range_subtype.eq_ignore_ascii_case(subtype) {
+            2
+        } else {
+            continue;
+        };
+
+        let q = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if best.is_none_or(|(best_spec, _)| specificity > best_spec) {
+            best = Some((specificity, q));
+        }
+    }
+
+    best.map_or(0.0, |(_, q)| q)
+}
+
+impl<T: Serialize> Negotiated<T> {
+    fn encode(
+        self,
+        codec: Codec,
+        content_type: &str,
+    ) -> Result<HttpResponse<response::Body>, BoxError> {
+        let buf = codec.encode(&self.0)?;
+
+        HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, buf.len())
+            .header(header::VARY, HeaderValue::from_static("accept"))
+            .body(response::Body::once(buf))
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+impl<T: Serialize> Response for Negotiated<T> {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK, StatusCode::NOT_ACCEPTABLE]
+    }
+
+    fn into_response(self) -> Result<HttpResponse<response::Body>, BoxError> {
+        self.encode(Codec::Json, Codec::Json.content_type())
+    }
+
+    fn into_response_for(self, request: &Parts) -> Result<HttpResponse<response::Body>, BoxError> {
+        let accept = match request.headers.get(header::ACCEPT) {
+            Some(accept) => accept.to_str().unwrap_or(""),
+            None => return self.into_response(),
+        };
+
+        match Codec::from_accept(accept) {
+            Some((codec, content_type)) => self.encode(codec, &content_type),
+            None => not_acceptable(),
+        }
+    }
+}
+
+fn not_acceptable() -> Result<HttpResponse<response::Body>, BoxError> {
+    let available: Vec<_> = CODECS.iter().map(|codec| codec.content_type()).collect();
+    let resp = format!(
+        "406 Not Acceptable - Available media types: {}",
+        available.join(", ")
+    );
+
+    HttpResponse::builder()
+        .status(StatusCode::NOT_ACCEPTABLE)
+        .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+        .header(header::CONTENT_LENGTH, resp.len())
+        .header(header::VARY, HeaderValue::from_static("accept"))
+        .body(response::Body::once(resp))
+        .map_err(|err| Box::new(err) as _)
+}
+
+impl<T> request::Body for Negotiated<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    const CONTENT_TYPE: &'static str = CONTENT_TYPE_JSON;
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        decode_with(Codec::Json, body)
+    }
+
+    fn decode_request(request: &Parts, body: BoxBody) -> DecodeResult<Self> {
+        let content_type = match request.headers.get(header::CONTENT_TYPE) {
+            Some(content_type) => content_type.to_str().unwrap_or(""),
+            None => return Self::decode(body),
+        };
+
+        match Codec::from_content_type(content_type) {
+            Some(codec) => decode_with(codec, body),
+            None => {
+                let err = format!("Unsupported media type {}", content_type);
+                Box::pin(async move { Err(err.into()) })
+            }
+        }
+    }
+
+    fn accepts(content_type: &str) -> bool {
+        Codec::from_content_type(content_type).is_some()
+    }
+}
+
+fn decode_with<T>(codec: Codec, body: BoxBody) -> DecodeResult<Negotiated<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    Box::pin(async move {
+        let bytes = request::to_bytes(body).await?;
+        Ok(Negotiated(codec.decode(&bytes)?))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept: &str) -> Option<(Codec, String)> {
+        Codec::from_accept(accept)
+    }
+
+    #[test]
+    fn quality_prefers_most_specific_range() {
+        let accept = "*/*;q=0.1, application/*;q=0.5, application/json;q=0.8";
+        assert_eq!(quality(accept, "application/json"), 0.8);
+        assert_eq!(quality(accept, "application/cbor"), 0.5);
+        assert_eq!(quality(accept, "text/plain"), 0.1);
+        assert_eq!(quality("text/html", "application/json"), 0.0);
+    }
+
+    #[test]
+    fn accept_picks_json() {
+        let json = Some((Codec::Json, CONTENT_TYPE_JSON.to_owned()));
+        assert_eq!(negotiate("application/json"), json);
+        assert_eq!(negotiate("*/*"), json);
+        assert_eq!(negotiate("text/html, application/*;q=0.2"), json);
+        assert_eq!(negotiate("text/html"), None);
+        assert_eq!(negotiate("application/json;q=0"), None);
+    }
+
+    #[test]
+    fn accept_suffixed_json() {
+        assert_eq!(
+            negotiate("application/problem+json"),
+            Some((Codec::Json, "application/problem+json".to_owned())),
+        );
+        assert_eq!(
+            negotiate("application/problem+json;q=0.5, application/json"),
+            Some((Codec::Json, CONTENT_TYPE_JSON.to_owned())),
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn accept_prefers_higher_quality() {
+        assert_eq!(
+            negotiate("application/json;q=0.5, application/msgpack"),
+            Some((Codec::MsgPack, "application/msgpack".to_owned())),
+        );
+        // ties go to the server preference
+        assert_eq!(
+            negotiate("application/msgpack, application/json"),
+            Some((Codec::Json, CONTENT_TYPE_JSON.to_owned())),
+        );
+    }
+
+    #[test]
+    fn content_type_matches_codec() {
+        assert_eq!(
+            Codec::from_content_type("application/json; charset=utf-8"),
+            Some(Codec::Json)
+        );
+        assert_eq!(
+            Codec::from_content_type("application/merge-patch+json"),
+            Some(Codec::Json)
+        );
+        assert_eq!(Codec::from_content_type("text/plain"), None);
+    }
+
+    #[test]
+    fn response_for_accept() {
+        let request = |accept: &str| {
+            http::Request::get("/")
+                .header(header::ACCEPT, accept)
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+
+        let resp = Negotiated(vec![1])
+            .into_response_for(&request("application/problem+json"))
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+
+        let resp = Negotiated(vec![1])
+            .into_response_for(&request("image/png"))
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+}
@@ -0,0 +1,381 @@
+//! Requests rejected by the generated server before reaching the handler.
+//!
+//! Every rejection keeps the original request so the fallback can reuse it.
+//! The request body is left empty if it's already consumed while decoding.
+//!
+//! They're rendered by the `render_rejection()` method the `#[api]` adds to the trait,
+//! which can be overridden to customize the responses.
+
+use http::{header, Method, Request, Response as HttpResponse, StatusCode};
+use http_body::LengthLimitError;
+
+use crate::request::BoxBody;
+use crate::response::{Body, Response, CONTENT_TYPE_TEXT};
+use crate::BoxError;
+
+pub type BoxRequest = Request<BoxBody>;
+
+/// Any of the rejections.
+///
+/// More rejections may be added, like the `InvalidUpgrade` of the `ws` feature.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Rejection {
+    NotFound(NotFound),
+    InvalidPath(InvalidPath),
+    MethodNotAllowed(MethodNotAllowed),
+    UnsupportedVersion(UnsupportedVersion),
+    InvalidPathParam(InvalidPathParam),
+    InvalidHostParam(InvalidHostParam),
+    InvalidQuery(InvalidQuery),
+    InvalidHeader(InvalidHeader),
+    UnsupportedMediaType(UnsupportedMediaType),
+    PayloadTooLarge(PayloadTooLarge),
+    InvalidBody(InvalidBody),
+    #[cfg(feature = "ws")]
+    InvalidUpgrade(InvalidUpgrade),
+}
+
+/// 404 Not Found
+///
+/// It is returned if the request doesn't match
+/// with any of the handler methods.
+#[derive(Debug, thiserror::Error)]
+#[error("404 Not Found")]
+pub struct NotFound {
+    pub request: BoxRequest,
+}
+
+/// 400 Bad Request - Invalid path
+///
+/// It is returned if the path can't be percent-decoded,
+/// or contains empty, `.` or `..` segments.
+#[derive(Debug, thiserror::Error)]
+#[error("400 Bad Request - Invalid path: {error}")]
+pub struct InvalidPath {
+    pub request: BoxRequest,
+    pub error: BoxError,
+}
+
+/// 405 Method Not Allowed
+///
+/// It is returned if the path matches with some handler methods,
+/// but none of them takes the request's method.
+#[derive(Debug, thiserror::Error)]
+#[error("405 Method Not Allowed")]
+pub struct MethodNotAllowed {
+    pub request: BoxRequest,
+    /// Methods the path accepts, sent as the `Allow` header.
+    pub allow: Vec<Method>,
+}
+
+/// 406 Not Acceptable - Unsupported version
+///
+/// It is returned if the API version requested by the header or the media type
+/// isn't declared by any of the handler methods.
+#[derive(Debug, thiserror::Error)]
+#[error("406 Not Acceptable - Unsupported version {version}")]
+pub struct UnsupportedVersion {
+    pub request: BoxRequest,
+    pub version: String,
+}
+
+/// 400 Bad Request - Invalid path parameter
+///
+/// It is returned if the path matches with a handler method
+/// but its parameter can't be parsed to the type the handler method expects,
+/// and no other handler method matches the request.
+#[derive(Debug, thiserror::Error)]
+#[error("400 Bad Request - Invalid path parameter {name}: {error}")]
+pub struct InvalidPathParam {
+    pub request: BoxRequest,
+    pub name: &'static str,
+    pub error: BoxError,
+}
+
+/// 400 Bad Request - Invalid host parameter
+///
+/// It is returned if the host matches with the `#[api(host = ...)]` pattern
+/// but its parameter can't be parsed to the type the handler method expects,
+/// and no other handler method matches the request.
+#[derive(Debug, thiserror::Error)]
+#[error("400 Bad Request - Invalid host parameter {name}: {error}")]
+pub struct InvalidHostParam {
+    pub request: BoxRequest,
+    pub name: &'static str,
+    pub error: BoxError,
+}
+
+/// 400 Bad Request - Invalid query
+///
+/// It is returned if the query string
+/// can't be decoded to the type the handler method expects.
+#[derive(Debug, thiserror::Error)]
+#[error("400 Bad Request - Invalid query: {error}")]
+pub struct InvalidQuery {
+    pub request: BoxRequest,
+    pub error: BoxError,
+}
+
+/// 400 Bad Request - Invalid header
+///
+/// It is returned if the request header is missing
+/// or can't be parsed to the type the handler method expects.
+#[derive(Debug, thiserror::Error)]
+#[error("400 Bad Request - Invalid header {name}: {error}")]
+pub struct InvalidHeader {
+    pub request: BoxRequest,
+    pub name: &'static str,
+    pub error: BoxError,
+}
+
+/// 415 Unsupported Media Type
+///
+/// It is returned if the request's `Content-Type`
+/// isn't acceptable for the body type the handler method expects.
+#[derive(Debug, thiserror::Error)]
+#[error("415 Unsupported Media Type - Expected {expected}")]
+pub struct UnsupportedMediaType {
+    pub request: BoxRequest,
+    pub expected: &'static str,
+}
+
+/// 413 Payload Too Large
+///
+/// It is returned if the request's body exceeds the length limit,
+/// like the one the `http_body::Limited` body sets.
+#[derive(Debug, thiserror::Error)]
+#[error("413 Payload Too Large")]
+pub struct PayloadTooLarge {
+    pub request: BoxRequest,
+}
+
+/// 400 Bad Request - Invalid body
+///
+/// It is returned if the request's body
+/// can't be decoded to the type the handler method expects.
+#[derive(Debug, thiserror::Error)]
+#[error("400 Bad Request - Invalid body: {error}")]
+pub struct InvalidBody {
+    pub request: BoxRequest,
+    pub error: BoxError,
+}
+
+/// 426 Upgrade Required or 400 Bad Request - Invalid WebSocket upgrade
+///
+/// It is returned if the request to the `#[websocket]` handler method
+/// isn't a valid WebSocket upgrade request.
+#[cfg(feature = "ws")]
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+pub struct InvalidUpgrade {
+    pub request: BoxRequest,
+    pub error: crate::ws::InvalidUpgrade,
+}
+
+impl Rejection {
+    /// Classifies the error from decoding the request body,
+    /// [`PayloadTooLarge`](PayloadTooLarge) if it's caused by the length limit
+    /// and [`InvalidBody`](InvalidBody) otherwise.
+    pub fn from_body_error(request: BoxRequest, error: BoxError) -> Self {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&*error);
+        while let Some(err) = source {
+            if err.is::<LengthLimitError>() {
+                return PayloadTooLarge { request }.into();
+            }
+            source = err.source();
+        }
+
+        InvalidBody { request, error }.into()
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Rejection::NotFound(_) => StatusCode::NOT_FOUND,
+            Rejection::InvalidPath(_) => StatusCode::BAD_REQUEST,
+            Rejection::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+            Rejection::UnsupportedVersion(_) => StatusCode::NOT_ACCEPTABLE,
+            Rejection::InvalidPathParam(_) => StatusCode::BAD_REQUEST,
+            Rejection::InvalidHostParam(_) => StatusCode::BAD_REQUEST,
+            Rejection::InvalidQuery(_) => StatusCode::BAD_REQUEST,
+            Rejection::InvalidHeader(_) => StatusCode::BAD_REQUEST,
+            Rejection::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Rejection::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Rejection::InvalidBody(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "ws")]
+            Rejection::InvalidUpgrade(v) => v.error.status(),
+        }
+    }
+
+    pub fn request(&self) -> &BoxRequest {
+        match self {
+            Rejection::NotFound(v) => &v.request,
+            Rejection::InvalidPath(v) => &v.request,
+            Rejection::MethodNotAllowed(v) => &v.request,
+            Rejection::UnsupportedVersion(v) => &v.request,
+            Rejection::InvalidPathParam(v) => &v.request,
+            Rejection::InvalidHostParam(v) => &v.request,
+            Rejection::InvalidQuery(v) => &v.request,
+            Rejection::InvalidHeader(v) => &v.request,
+            Rejection::UnsupportedMediaType(v) => &v.request,
+            Rejection::PayloadTooLarge(v) => &v.request,
+            Rejection::InvalidBody(v) => &v.request,
+            #[cfg(feature = "ws")]
+            Rejection::InvalidUpgrade(v) => &v.request,
+        }
+    }
+
+    pub fn into_request(self) -> BoxRequest {
+        match self {
+            Rejection::NotFound(v) => v.request,
+            Rejection::InvalidPath(v) => v.request,
+            Rejection::MethodNotAllowed(v) => v.request,
+            Rejection::UnsupportedVersion(v) => v.request,
+            Rejection::InvalidPathParam(v) => v.request,
+            Rejection::InvalidHostParam(v) => v.request,
+            Rejection::InvalidQuery(v) => v.request,
+            Rejection::InvalidHeader(v) => v.request,
+            Rejection::UnsupportedMediaType(v) => v.request,
+            Rejection::PayloadTooLarge(v) => v.request,
+            Rejection::InvalidBody(v) => v.request,
+            #[cfg(feature = "ws")]
+            Rejection::InvalidUpgrade(v) => v.request,
+        }
+    }
+}
+
+impl Response for Rejection {
+    fn statuses() -> Vec<StatusCode> {
+        #[allow(unused_mut)]
+        let mut statuses = vec![
+            StatusCode::NOT_FOUND,
+            StatusCode::METHOD_NOT_ALLOWED,
+            StatusCode::NOT_ACCEPTABLE,
+            StatusCode::BAD_REQUEST,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            StatusCode::PAYLOAD_TOO_LARGE,
+        ];
+        #[cfg(feature = "ws")]
+        statuses.push(StatusCode::UPGRADE_REQUIRED);
+        statuses
+    }
+
+    fn into_response(self) -> Result<HttpResponse<Body>, BoxError> {
+        match self {
+            Rejection::NotFound(v) => v.into_response(),
+            Rejection::InvalidPath(v) => v.into_response(),
+            Rejection::MethodNotAllowed(v) => v.into_response(),
+            Rejection::UnsupportedVersion(v) => v.into_response(),
+            Rejection::InvalidPathParam(v) => v.into_response(),
+            Rejection::InvalidHostParam(v) => v.into_response(),
+            Rejection::InvalidQuery(v) => v.into_response(),
+            Rejection::InvalidHeader(v) => v.into_response(),
+            Rejection::UnsupportedMediaType(v) => v.into_response(),
+            Rejection::PayloadTooLarge(v) => v.into_response(),
+            Rejection::InvalidBody(v) => v.into_response(),
+            #[cfg(feature = "ws")]
+            Rejection::InvalidUpgrade(v) => v.into_response(),
+        }
+    }
+}
+
+impl Response for MethodNotAllowed {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::METHOD_NOT_ALLOWED]
+    }
+
+    fn into_response(self) -> Result<HttpResponse<Body>, BoxError> {
+        let allow: Vec<_> = self.allow.iter().map(Method::as_str).collect();
+        let resp = self.to_string();
+
+        HttpResponse::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+            .header(header::CONTENT_LENGTH, resp.len())
+            .header(header::ALLOW, allow.join(", "))
+            .body(Body::once(resp))
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+/// Keeps the headers the WebSocket client needs to retry.
+#[cfg(feature = "ws")]
+impl Response for InvalidUpgrade {
+    fn statuses() -> Vec<StatusCode> {
+        crate::ws::InvalidUpgrade::statuses()
+    }
+
+    fn into_response(self) -> Result<HttpResponse<Body>, BoxError> {
+        self.error.into_response()
+    }
+}
+
+macro_rules! impl_rejection {
+    ($($name:ident,)*) => {$(
+        impl From<$name> for Rejection {
+            fn from(v: $name) -> Self {
+                Rejection::$name(v)
+            }
+        }
+
+        impl From<$name> for BoxRequest {
+            fn from(v: $name) -> Self {
+                v.request
+            }
+        }
+    )*};
+}
+
+/// Renders the rejection as its `Display` message in plain text.
+macro_rules! impl_text_response {
+    ($($name:ident => $status:ident,)*) => {$(
+        impl Response for $name {
+            fn statuses() -> Vec<StatusCode> {
+                vec![StatusCode::$status]
+            }
+
+            fn into_response(self) -> Result<HttpResponse<Body>, BoxError> {
+                let resp = self.to_string();
+
+                HttpResponse::builder()
+                    .status(StatusCode::$status)
+                    .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+                    .header(header::CONTENT_LENGTH, resp.len())
+                    .body(Body::once(resp))
+                    .map_err(|err| Box::new(err) as _)
+            }
+        }
+    )*};
+}
+
+impl_rejection! {
+    NotFound,
+    InvalidPath,
+    MethodNotAllowed,
+    UnsupportedVersion,
+    InvalidPathParam,
+    InvalidHostParam,
+    InvalidQuery,
+    InvalidHeader,
+    UnsupportedMediaType,
+    PayloadTooLarge,
+    InvalidBody,
+}
+
+#[cfg(feature = "ws")]
+impl_rejection! {
+    InvalidUpgrade,
+}
+
+impl_text_response! {
+    NotFound => NOT_FOUND,
+    InvalidPath => BAD_REQUEST,
+    UnsupportedVersion => NOT_ACCEPTABLE,
+    InvalidPathParam => BAD_REQUEST,
+    InvalidHostParam => BAD_REQUEST,
+    InvalidQuery => BAD_REQUEST,
+    InvalidHeader => BAD_REQUEST,
+    UnsupportedMediaType => UNSUPPORTED_MEDIA_TYPE,
+    PayloadTooLarge => PAYLOAD_TOO_LARGE,
+    InvalidBody => BAD_REQUEST,
+}
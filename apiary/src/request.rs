@@ -1,4 +1,242 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
 
+use bytes::{Buf, Bytes, BytesMut};
+use http::request::Parts;
+use http::{HeaderValue, Request};
+use http_body::Body as HttpBody;
+
+use crate::BoxError;
+
+mod stream;
+
+#[cfg(feature = "macro")]
+pub use apiary_macro::Body;
+pub use stream::ByteStream;
+#[cfg(feature = "serde")]
+pub use stream::{JsonLines, JsonLinesError};
+
+pub type BoxBody = http_body::combinators::BoxBody<Bytes, BoxError>;
+
+pub type DecodeResult<T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send + 'static>>;
+
+/// Type which can be decoded from the request body.
 pub trait Body: Sized {
     const CONTENT_TYPE: &'static str;
+
+    fn decode(body: BoxBody) -> DecodeResult<Self>;
+
+    /// Decodes the body of the given request.
+    ///
+    /// Types like `Negotiated` override it to take the request headers into account.
+    /// Wrapper types should forward it to the inner type.
+    fn decode_request(request: &Parts, body: BoxBody) -> DecodeResult<Self> {
+        let _ = request;
+        Self::decode(body)
+    }
+
+    /// Checks whether the body can be decoded from the given `Content-Type`.
+    ///
+    /// The generated server answers `415 Unsupported Media Type` if it returns `false`.
+    /// Requests without the `Content-Type` header are always decoded.
+    fn accepts(content_type: &str) -> bool {
+        let _ = content_type;
+        true
+    }
+}
+
+/// Textual parameter extracted from the request, like the path segment or the header.
+///
+/// Implemented for every `FromStr` type whose error converts into the `BoxError`.
+/// The `#[api]` handlers can also take the `Result<T, BoxError>` parameter,
+/// which gets the parse error instead of rejecting the request,
+/// and the `Option<T>` header parameter, which is `None` if the header is missing.
+pub trait Param: Sized {
+    fn from_param(param: &str) -> Result<Self, BoxError>;
+}
+
+/// Value of the header parameter, used by the generated server.
+pub fn header_str(value: Option<&HeaderValue>) -> Result<Option<&str>, BoxError> {
+    match value {
+        Some(value) => Ok(Some(value.to_str()?)),
+        None => Ok(None),
+    }
+}
+
+/// Media type of the `Content-Type` without its parameters, like `application/json`.
+#[cfg(any(feature = "serde", feature = "multipart"))]
+pub(crate) fn media_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or("").trim()
+}
+
+/// Converts any HTTP body into the [`BoxBody`](BoxBody).
+pub fn boxed<B>(body: B) -> BoxBody
+where
+    B: HttpBody + Send + Sync + 'static,
+    B::Error: Into<BoxError>,
+{
+    body.map_data(|mut data| data.copy_to_bytes(data.remaining()))
+        .map_err(Into::into)
+        .boxed()
+}
+
+/// Copies the request head without its extensions, which can't be cloned.
+pub fn clone_head<B>(request: &Request<B>) -> Parts {
+    let mut head = Request::new(());
+    *head.method_mut() = request.method().clone();
+    *head.uri_mut() = request.uri().clone();
+    *head.version_mut() = request.version();
+    *head.headers_mut() = request.headers().clone();
+    head.into_parts().0
+}
+
+/// Collects the whole body into a single buffer.
+pub async fn to_bytes(mut body: BoxBody) -> Result<Bytes, BoxError> {
+    let first = match body.data().await {
+        Some(data) => data?,
+        None => return Ok(Bytes::new()),
+    };
+    let second = match body.data().await {
+        Some(data) => data?,
+        None => return Ok(first),
+    };
+
+    let mut buf = BytesMut::with_capacity(first.len() + second.len());
+    buf.extend_from_slice(&first);
+    buf.extend_from_slice(&second);
+
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data?);
+    }
+
+    Ok(buf.freeze())
+}
+
+impl<T: Body + Send + 'static> Body for Result<T, BoxError> {
+    const CONTENT_TYPE: &'static str = T::CONTENT_TYPE;
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        let fut = T::decode(body);
+        Box::pin(async move { Ok(fut.await) })
+    }
+
+    fn decode_request(request: &Parts, body: BoxBody) -> DecodeResult<Self> {
+        let fut = T::decode_request(request, body);
+        Box::pin(async move { Ok(fut.await) })
+    }
+
+    fn accepts(content_type: &str) -> bool {
+        T::accepts(content_type)
+    }
+}
+
+impl Body for Bytes {
+    const CONTENT_TYPE: &'static str = "application/octet-stream";
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(to_bytes(body))
+    }
+}
+
+impl Body for Vec<u8> {
+    const CONTENT_TYPE: &'static str = "application/octet-stream";
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(async move { Ok(to_bytes(body).await?.to_vec()) })
+    }
+}
+
+impl Body for String {
+    const CONTENT_TYPE: &'static str = crate::response::CONTENT_TYPE_TEXT;
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(async move {
+            let bytes = to_bytes(body).await?;
+            Ok(std::str::from_utf8(&bytes)?.to_owned())
+        })
+    }
+}
+
+impl<T> Param for T
+where
+    T: FromStr,
+    T::Err: Into<BoxError>,
+{
+    fn from_param(param: &str) -> Result<Self, BoxError> {
+        param.parse().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::task::{Context, Poll};
+
+    use http::HeaderMap;
+
+    use super::*;
+
+    /// Body which sends the chunks one by one.
+    struct Chunks(VecDeque<&'static [u8]>);
+
+    impl HttpBody for Chunks {
+        type Data = Bytes;
+        type Error = BoxError;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, BoxError>>> {
+            Poll::Ready(self.0.pop_front().map(|chunk| Ok(Bytes::from(chunk))))
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, BoxError>> {
+            Poll::Ready(Ok(None))
+        }
+    }
+
+    pub(super) fn chunks(chunks: &[&'static [u8]]) -> BoxBody {
+        boxed(Chunks(chunks.iter().copied().collect()))
+    }
+
+    #[tokio::test]
+    async fn to_bytes_joins_chunks() {
+        assert_eq!(to_bytes(chunks(&[])).await.unwrap(), "");
+        assert_eq!(to_bytes(chunks(&[b"ab"])).await.unwrap(), "ab");
+        assert_eq!(
+            to_bytes(chunks(&[b"ab", b"", b"cd", b"e"])).await.unwrap(),
+            "abcde"
+        );
+    }
+
+    #[tokio::test]
+    async fn decode_builtin_bodies() {
+        let body = || chunks(&[b"hello ", b"world"]);
+
+        assert_eq!(Bytes::decode(body()).await.unwrap(), "hello world");
+        assert_eq!(Vec::<u8>::decode(body()).await.unwrap(), b"hello world");
+        assert_eq!(String::decode(body()).await.unwrap(), "hello world");
+        assert!(String::decode(chunks(&[b"\xff"])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn decode_result_keeps_error() {
+        let decoded = <Result<String, BoxError>>::decode(chunks(&[b"ok"])).await;
+        assert_eq!(decoded.unwrap().unwrap(), "ok");
+
+        let decoded = <Result<String, BoxError>>::decode(chunks(&[b"\xff"])).await;
+        assert!(decoded.unwrap().is_err());
+    }
+
+    #[test]
+    fn builtin_bodies_accept_any_type() {
+        assert_eq!(String::CONTENT_TYPE, crate::response::CONTENT_TYPE_TEXT);
+        assert_eq!(Bytes::CONTENT_TYPE, "application/octet-stream");
+        assert!(Bytes::accepts("image/png"));
+        assert!(<Result<Vec<u8>, BoxError>>::accepts("text/plain"));
+    }
 }
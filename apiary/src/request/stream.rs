@@ -0,0 +1,218 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use http_body::Body as HttpBody;
+
+use crate::request::{Body, BoxBody, DecodeResult};
+use crate::BoxError;
+
+#[cfg(feature = "serde")]
+pub use json_lines::{JsonLines, JsonLinesError};
+
+/// Request body streamed as chunks as they arrive, instead of buffered in memory.
+#[derive(Debug)]
+pub struct ByteStream {
+    body: BoxBody,
+}
+
+impl ByteStream {
+    /// Waits for the next chunk, returns `None` if the body is ended.
+    pub async fn next_chunk(&mut self) -> Option<Result<Bytes, BoxError>> {
+        self.body.data().await
+    }
+}
+
+impl Body for ByteStream {
+    const CONTENT_TYPE: &'static str = "application/octet-stream";
+
+    fn decode(body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(async move { Ok(ByteStream { body }) })
+    }
+}
+
+impl Stream for ByteStream {
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.body).poll_data(cx)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod json_lines {
+    use std::fmt;
+    use std::future::poll_fn;
+    use std::marker::PhantomData;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use bytes::BytesMut;
+    use futures_core::Stream;
+    use serde::de::DeserializeOwned;
+
+    use super::ByteStream;
+    use crate::request::{self, Body, BoxBody, DecodeResult};
+    use crate::BoxError;
+
+    /// Newline delimited JSON body, each line is deserialized lazily as it arrives.
+    ///
+    /// Blank lines are skipped.
+    pub struct JsonLines<T> {
+        stream: ByteStream,
+        buf: BytesMut,
+        /// Bytes of the `buf` already searched for the newline.
+        scanned: usize,
+        line: usize,
+        ended: bool,
+        _marker: PhantomData<fn() -> T>,
+    }
+
+    /// Failed to deserialize the line of the [`JsonLines`](JsonLines) body.
+    #[derive(Debug, thiserror::Error)]
+    #[error("line {line}: {source}")]
+    pub struct JsonLinesError {
+        /// Line number, starting from 1.
+        pub line: usize,
+        pub source: serde_json::Error,
+    }
+
+    impl<T: DeserializeOwned> JsonLines<T> {
+        /// Waits for the next row, returns `None` if the body is ended.
+        pub async fn next_row(&mut self) -> Option<Result<T, BoxError>> {
+            poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+        }
+    }
+
+    impl<T> fmt::Debug for JsonLines<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("JsonLines")
+                .field("line", &self.line)
+                .field("ended", &self.ended)
+                .finish()
+        }
+    }
+
+    impl<T: DeserializeOwned> Body for JsonLines<T> {
+        const CONTENT_TYPE: &'static str = "application/x-ndjson";
+
+        fn decode(body: BoxBody) -> DecodeResult<Self> {
+            Box::pin(async move {
+                Ok(JsonLines {
+                    stream: ByteStream { body },
+                    buf: BytesMut::new(),
+                    scanned: 0,
+                    line: 0,
+                    ended: false,
+                    _marker: PhantomData,
+                })
+            })
+        }
+
+        /// Accepts `application/x-ndjson`, `application/jsonl` and `application/x-jsonlines`.
+        fn accepts(content_type: &str) -> bool {
+            let media_type = request::media_type(content_type);
+            [
+                "application/x-ndjson",
+                "application/jsonl",
+                "application/x-jsonlines",
+            ]
+            .iter()
+            .any(|ty| media_type.eq_ignore_ascii_case(ty))
+        }
+    }
+
+    impl<T: DeserializeOwned> JsonLines<T> {
+        fn parse_line(&mut self, len: usize) -> Option<Result<T, BoxError>> {
+            let line = self.buf.split_to(len);
+            self.scanned = 0;
+            self.line += 1;
+
+            let line = line.strip_suffix(b"\n").unwrap_or(&line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line.iter().all(u8::is_ascii_whitespace) {
+                return None;
+            }
+
+            Some(serde_json::from_slice(line).map_err(|source| {
+                Box::new(JsonLinesError {
+                    line: self.line,
+                    source,
+                }) as _
+            }))
+        }
+    }
+
+    impl<T: DeserializeOwned> Stream for JsonLines<T> {
+        type Item = Result<T, BoxError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = &mut *self;
+
+            loop {
+                if let Some(idx) = this.buf[this.scanned..].iter().position(|&b| b == b'\n') {
+                    match this.parse_line(this.scanned + idx + 1) {
+                        Some(row) => return Poll::Ready(Some(row)),
+                        None => continue,
+                    }
+                }
+                this.scanned = this.buf.len();
+
+                if this.ended {
+                    // the last line without the trailing newline
+                    if this.buf.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    match this.parse_line(this.buf.len()) {
+                        Some(row) => return Poll::Ready(Some(row)),
+                        None => return Poll::Ready(None),
+                    }
+                }
+
+                match Pin::new(&mut this.stream).poll_next(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => this.buf.extend_from_slice(&chunk),
+                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                    Poll::Ready(None) => this.ended = true,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        async fn rows(chunks: &[&'static str]) -> Vec<Result<u32, BoxError>> {
+            let chunks: Vec<_> = chunks.iter().map(|chunk| chunk.as_bytes()).collect();
+            let body = request::tests::chunks(&chunks);
+            let mut lines = JsonLines::<u32>::decode(body).await.unwrap();
+
+            let mut rows = vec![];
+            while let Some(row) = lines.next_row().await {
+                rows.push(row);
+            }
+            rows
+        }
+
+        #[tokio::test]
+        async fn lines_across_chunks() {
+            let rows = rows(&["1\n2", "2\r\n\n  \n3", "3\n", "4"]).await;
+            let rows: Vec<_> = rows.into_iter().map(Result::unwrap).collect();
+            assert_eq!(rows, [1, 22, 33, 4]);
+        }
+
+        #[tokio::test]
+        async fn error_line_number() {
+            let rows = rows(&["1\n\n", "x\n3\n"]).await;
+            assert_eq!(rows.len(), 3);
+            assert!(rows[0].is_ok());
+            assert!(rows[2].is_ok());
+
+            let err = rows.into_iter().nth(1).unwrap().unwrap_err();
+            let err = err.downcast::<JsonLinesError>().unwrap();
+            assert_eq!(err.line, 3);
+        }
+    }
+}
@@ -1,33 +1,79 @@
+use http::request::Parts;
 use http::{header, Response as HttpResponse, StatusCode};
 
 use crate::BoxError;
 
 mod body;
+mod sse;
 
-pub use body::Body;
+pub use body::{Body, Sender, Trailers};
+pub use sse::{Event, Sse};
 
 pub(crate) const CONTENT_TYPE_TEXT: &str = "text/plain; charset=utf-8";
 
 pub trait Response {
     fn into_response(self) -> Result<http::Response<Body>, BoxError>;
+
+    /// Converts into the response for the given request.
+    ///
+    /// Types like `Negotiated` override it to take the request headers into account.
+    /// Wrapper types should forward it to the inner value.
+    fn into_response_for(self, request: &Parts) -> Result<http::Response<Body>, BoxError>
+    where
+        Self: Sized,
+    {
+        let _ = request;
+        self.into_response()
+    }
+
+    /// Status codes this type may respond with, if known ahead of time.
+    fn statuses() -> Vec<StatusCode>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
 }
 
 impl<T: Response, E: Response> Response for Result<T, E> {
+    fn statuses() -> Vec<StatusCode> {
+        let mut statuses = T::statuses();
+        for status in E::statuses() {
+            if !statuses.contains(&status) {
+                statuses.push(status);
+            }
+        }
+        statuses
+    }
+
     fn into_response(self) -> Result<http::Response<Body>, BoxError> {
         match self {
             Ok(t) => t.into_response(),
-            Err(e) => {
-                let mut resp = e.into_response()?;
-                if resp.status() == StatusCode::OK {
-                    *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR
-                }
-                Ok(resp)
-            }
+            Err(e) => server_error(e.into_response()?),
+        }
+    }
+
+    fn into_response_for(self, request: &Parts) -> Result<http::Response<Body>, BoxError> {
+        match self {
+            Ok(t) => t.into_response_for(request),
+            Err(e) => server_error(e.into_response_for(request)?),
         }
     }
 }
 
+/// Error response without the specific status is treated as the server error.
+fn server_error(mut resp: http::Response<Body>) -> Result<http::Response<Body>, BoxError> {
+    if resp.status() == StatusCode::OK {
+        *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR
+    }
+    Ok(resp)
+}
+
 impl Response for () {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
     fn into_response(self) -> Result<http::Response<Body>, BoxError> {
         HttpResponse::builder()
             .status(StatusCode::OK)
@@ -39,6 +85,10 @@ impl Response for () {
 }
 
 impl Response for String {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
     fn into_response(self) -> Result<http::Response<Body>, BoxError> {
         HttpResponse::builder()
             .status(StatusCode::OK)
@@ -50,6 +100,10 @@ impl Response for String {
 }
 
 impl Response for &'static str {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
     fn into_response(self) -> Result<http::Response<Body>, BoxError> {
         HttpResponse::builder()
             .status(StatusCode::OK)
@@ -61,6 +115,10 @@ impl Response for &'static str {
 }
 
 impl Response for Vec<u8> {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
     fn into_response(self) -> Result<http::Response<Body>, BoxError> {
         HttpResponse::builder()
             .status(StatusCode::OK)
@@ -71,7 +129,35 @@ impl Response for Vec<u8> {
     }
 }
 
+impl Response for HttpResponse<Body> {
+    fn into_response(self) -> Result<http::Response<Body>, BoxError> {
+        Ok(self)
+    }
+}
+
+/// Possibly streaming binary body, without the `Content-Length` unless its size is known.
+impl Response for Body {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
+    fn into_response(self) -> Result<http::Response<Body>, BoxError> {
+        let mut resp = HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/octet-stream");
+        if let Some(len) = http_body::Body::size_hint(&self).exact() {
+            resp = resp.header(header::CONTENT_LENGTH, len);
+        }
+
+        resp.body(self).map_err(|err| Box::new(err) as _)
+    }
+}
+
 impl Response for BoxError {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::INTERNAL_SERVER_ERROR]
+    }
+
     fn into_response(self) -> Result<http::Response<Body>, BoxError> {
         let msg = self.to_string();
 
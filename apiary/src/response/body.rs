@@ -1,8 +1,14 @@
+use std::fmt;
+use std::future::poll_fn;
 use std::mem;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 
 use bytes::Bytes;
+use futures_channel::mpsc;
+use futures_core::Stream;
+use http::header::{HeaderMap, HeaderValue, IntoHeaderName};
 use http_body::SizeHint;
 use pin_project::pin_project;
 
@@ -13,6 +19,7 @@ use crate::BoxError;
 pub struct Body {
     #[pin]
     repr: Repr,
+    trailers: Option<Trailers>,
 }
 
 #[pin_project(project = Proj)]
@@ -20,11 +27,34 @@ pub struct Body {
 enum Repr {
     Empty,
     Once(Bytes),
+    Channel(mpsc::Receiver<Result<Bytes, BoxError>>),
+    Stream(BoxStream),
+}
+
+struct BoxStream(Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + Send + 'static>>);
+
+/// Trailers sent after the body created by the [`Body::with_trailers()`](Body::with_trailers).
+///
+/// Only the fields inserted before the body ends are sent.
+#[derive(Debug, Clone, Default)]
+pub struct Trailers {
+    map: Arc<Mutex<HeaderMap>>,
+}
+
+/// Sending half of the [`Body::channel()`](Body::channel).
+///
+/// The body ends when every senders are dropped.
+#[derive(Debug, Clone)]
+pub struct Sender {
+    tx: mpsc::Sender<Result<Bytes, BoxError>>,
 }
 
 impl Body {
     pub fn empty() -> Self {
-        Body { repr: Repr::Empty }
+        Body {
+            repr: Repr::Empty,
+            trailers: None,
+        }
     }
 
     pub fn once<T: Into<Bytes>>(bytes: T) -> Self {
@@ -37,8 +67,83 @@ impl Body {
             } else {
                 Repr::Once(bytes)
             },
+            trailers: None,
         }
     }
+
+    /// Creates the body which streams the chunks sent from the returned [`Sender`](Sender).
+    ///
+    /// The sender waits until the previous chunk is consumed, so the slow client
+    /// slows down the producer instead of buffering the whole body in memory.
+    pub fn channel() -> (Sender, Self) {
+        let (tx, rx) = mpsc::channel(0);
+
+        (
+            Sender { tx },
+            Body {
+                repr: Repr::Channel(rx),
+                trailers: None,
+            },
+        )
+    }
+
+    /// Creates the body which streams the chunks from the `stream`.
+    ///
+    /// The stream is polled only when the client is ready to receive the next chunk.
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, BoxError>> + Send + 'static,
+    {
+        Body {
+            repr: Repr::Stream(BoxStream(Box::pin(stream))),
+            trailers: None,
+        }
+    }
+
+    /// Attaches the trailers, which the producer fills while streaming the body.
+    ///
+    /// Trailers are sent over HTTP/2, but the hyper drops them on HTTP/1.1 connections.
+    pub fn with_trailers(mut self) -> (Self, Trailers) {
+        let trailers = self.trailers.get_or_insert_with(Trailers::default).clone();
+        (self, trailers)
+    }
+}
+
+impl Trailers {
+    /// Inserts the field, replacing the previous values of the same name.
+    pub fn insert<K: IntoHeaderName>(&self, name: K, value: HeaderValue) {
+        self.lock().insert(name, value);
+    }
+
+    /// Appends the field, keeping the previous values of the same name.
+    pub fn append<K: IntoHeaderName>(&self, name: K, value: HeaderValue) {
+        self.lock().append(name, value);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HeaderMap> {
+        // the map can't be left in an inconsistent state on panic
+        self.map.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Sender {
+    /// Sends the chunk, waiting until the body is ready to take it.
+    ///
+    /// Returns error if the body is dropped, usually because the client is disconnected.
+    pub async fn send_data(&mut self, data: Bytes) -> Result<(), BoxError> {
+        self.send(Ok(data)).await
+    }
+
+    /// Aborts the body with the error.
+    pub async fn send_error(&mut self, err: BoxError) -> Result<(), BoxError> {
+        self.send(Err(err)).await
+    }
+
+    async fn send(&mut self, item: Result<Bytes, BoxError>) -> Result<(), BoxError> {
+        poll_fn(|cx| self.tx.poll_ready(cx)).await?;
+        self.tx.start_send(item)?;
+        Ok(())
+    }
 }
 
 impl http_body::Body for Body {
@@ -47,33 +152,164 @@ impl http_body::Body for Body {
 
     fn poll_data(
         mut self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
-        match self.as_mut().project().repr.project() {
-            Proj::Empty => Poll::Ready(None),
+        let next = match self.as_mut().project().repr.project() {
+            Proj::Empty => return Poll::Ready(None),
             Proj::Once(b) => {
                 let b = mem::take(b);
-                self.set(Body::empty());
-                Poll::Ready(Some(Ok(b)))
+                self.project().repr.set(Repr::Empty);
+                return Poll::Ready(Some(Ok(b)));
+            }
+            Proj::Channel(rx) => Pin::new(rx).poll_next(cx),
+            Proj::Stream(stream) => stream.0.as_mut().poll_next(cx),
+        };
+
+        match next {
+            Poll::Ready(None) => {
+                self.project().repr.set(Repr::Empty);
+                Poll::Ready(None)
             }
+            other => other,
         }
     }
 
     fn poll_trailers(
         self: Pin<&mut Self>,
         _cx: &mut Context<'_>,
-    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
-        Poll::Ready(Ok(None))
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let trailers = match self.project().trailers.take() {
+            Some(trailers) => mem::take(&mut *trailers.lock()),
+            None => return Poll::Ready(Ok(None)),
+        };
+
+        Poll::Ready(Ok(if trailers.is_empty() {
+            None
+        } else {
+            Some(trailers)
+        }))
     }
 
     fn is_end_stream(&self) -> bool {
-        matches!(&self.repr, Repr::Empty)
+        matches!(&self.repr, Repr::Empty) && self.trailers.is_none()
     }
 
     fn size_hint(&self) -> SizeHint {
         match &self.repr {
             Repr::Empty => SizeHint::with_exact(0),
             Repr::Once(b) => SizeHint::with_exact(b.len() as u64),
+            Repr::Channel(_) | Repr::Stream(_) => SizeHint::default(),
         }
     }
 }
+
+impl fmt::Debug for BoxStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxStream").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body::Body as _;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn channel() {
+        let (mut tx, mut body) = Body::channel();
+        assert_eq!(body.size_hint().exact(), None);
+
+        let send = tokio::spawn(async move {
+            tx.send_data(Bytes::from("a")).await.unwrap();
+            tx.send_data(Bytes::from("b")).await.unwrap();
+        });
+
+        assert_eq!(body.data().await.unwrap().unwrap(), "a");
+        assert_eq!(body.data().await.unwrap().unwrap(), "b");
+        send.await.unwrap();
+
+        // every senders are dropped
+        assert!(body.data().await.is_none());
+        assert!(body.is_end_stream());
+        assert_eq!(body.size_hint().exact(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn channel_error() {
+        let (mut tx, mut body) = Body::channel();
+
+        let send = tokio::spawn(async move {
+            tx.send_error("aborted".into()).await.unwrap();
+        });
+
+        let err = body.data().await.unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "aborted");
+        send.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_to_dropped_body() {
+        let (mut tx, body) = Body::channel();
+        drop(body);
+        assert!(tx.send_data(Bytes::from("a")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stream() {
+        let (tx, rx) = mpsc::unbounded();
+        tx.unbounded_send(Ok(Bytes::from("a"))).unwrap();
+        tx.unbounded_send(Err("aborted".into())).unwrap();
+        drop(tx);
+
+        let mut body = Body::from_stream(rx);
+        assert_eq!(body.size_hint().exact(), None);
+        assert!(!body.is_end_stream());
+
+        assert_eq!(body.data().await.unwrap().unwrap(), "a");
+        assert!(body.data().await.unwrap().is_err());
+        assert!(body.data().await.is_none());
+        assert!(body.is_end_stream());
+    }
+
+    #[tokio::test]
+    async fn trailers_after_data() {
+        let (mut tx, body) = Body::channel();
+        let (mut body, trailers) = body.with_trailers();
+
+        let send = tokio::spawn(async move {
+            tx.send_data(Bytes::from("a")).await.unwrap();
+            trailers.insert("x-checksum", HeaderValue::from_static("1"));
+            trailers.append("x-checksum", HeaderValue::from_static("2"));
+            trailers.insert("x-count", HeaderValue::from_static("1"));
+        });
+
+        assert_eq!(body.data().await.unwrap().unwrap(), "a");
+        send.await.unwrap();
+        assert!(body.data().await.is_none());
+        assert!(!body.is_end_stream());
+
+        let trailers = body.trailers().await.unwrap().unwrap();
+        let checksums: Vec<_> = trailers.get_all("x-checksum").iter().collect();
+        assert_eq!(checksums, ["1", "2"]);
+        assert_eq!(trailers["x-count"], "1");
+
+        assert!(body.is_end_stream());
+        assert!(body.trailers().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn empty_trailers() {
+        let (mut body, _trailers) = Body::once("a").with_trailers();
+        assert_eq!(body.data().await.unwrap().unwrap(), "a");
+        assert!(body.data().await.is_none());
+        assert!(body.trailers().await.unwrap().is_none());
+    }
+
+    #[test]
+    fn once_size_hint() {
+        assert_eq!(Body::once("abc").size_hint().exact(), Some(3));
+        assert!(Body::once("").is_end_stream());
+        assert!(Body::empty().is_end_stream());
+    }
+}
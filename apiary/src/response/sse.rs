@@ -0,0 +1,269 @@
+use std::fmt::Write as _;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_core::Stream;
+use http::{header, HeaderValue, Response as HttpResponse, StatusCode};
+use tokio::time::{sleep, Instant, Sleep};
+
+use crate::response::{Body, Response};
+use crate::BoxError;
+
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Server-Sent Events response.
+///
+/// It streams each event from the stream as it arrives,
+/// and sends a comment as a keep-alive when there's no event for a while.
+///
+/// The client resends the `id` of the last event it received
+/// as the `Last-Event-ID` request header on reconnection,
+/// which can be taken as a `#[header] last_event_id: Option<String>` handler argument.
+#[derive(Debug)]
+pub struct Sse<S> {
+    events: S,
+    keep_alive: Option<Duration>,
+}
+
+/// Single event of the [`Sse`](Sse) response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+impl<S> Sse<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    pub fn new(events: S) -> Self {
+        Sse {
+            events,
+            keep_alive: Some(DEFAULT_KEEP_ALIVE),
+        }
+    }
+
+    /// Sets the interval of the keep-alive comments, 15 seconds by default.
+    /// `None` disables them.
+    pub fn keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+}
+
+impl Event {
+    /// Sets the `data` field. Multi-line data is sent as multiple `data` lines.
+    pub fn data<T: Into<String>>(mut self, data: T) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Sets the `data` field as the JSON serialized value.
+    #[cfg(feature = "serde")]
+    pub fn json_data<T: serde::Serialize>(self, data: &T) -> Result<Self, BoxError> {
+        Ok(self.data(serde_json::to_string(data)?))
+    }
+
+    /// Sets the `id` field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id contains a newline or a null character.
+    pub fn id<T: Into<String>>(mut self, id: T) -> Self {
+        let id = id.into();
+        assert!(
+            !id.contains(['\r', '\n', '\0']),
+            "SSE event id can't contain newlines or null characters"
+        );
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the `event` field, the type of the event.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the event type contains a newline.
+    pub fn event<T: Into<String>>(mut self, event: T) -> Self {
+        let event = event.into();
+        assert!(
+            !event.contains(['\r', '\n']),
+            "SSE event type can't contain newlines"
+        );
+        self.event = Some(event);
+        self
+    }
+
+    /// Sets the `retry` field, the reconnection time of the client.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Sets the comment, which is ignored by the client.
+    pub fn comment<T: Into<String>>(mut self, comment: T) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    fn encode(&self) -> Bytes {
+        let mut buf = String::new();
+
+        let mut lines = |field: &str, value: &str| {
+            for line in value.split("\r\n").flat_map(|l| l.split(['\r', '\n'])) {
+                let _ = writeln!(buf, "{}: {}", field, line);
+            }
+        };
+
+        if let Some(comment) = &self.comment {
+            lines("", comment);
+        }
+        if let Some(id) = &self.id {
+            lines("id", id);
+        }
+        if let Some(event) = &self.event {
+            lines("event", event);
+        }
+        if let Some(retry) = self.retry {
+            lines("retry", &retry.as_millis().to_string());
+        }
+        if let Some(data) = &self.data {
+            lines("data", data);
+        }
+        buf.push('\n');
+
+        buf.into()
+    }
+}
+
+impl<S> Response for Sse<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::OK]
+    }
+
+    fn into_response(self) -> Result<HttpResponse<Body>, BoxError> {
+        let stream = SseStream {
+            events: Box::pin(self.events),
+            keep_alive: self.keep_alive,
+            timer: None,
+        };
+
+        HttpResponse::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            // disables the response buffering of the reverse proxies like nginx
+            .header("x-accel-buffering", HeaderValue::from_static("no"))
+            .body(Body::from_stream(stream))
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+struct SseStream<S> {
+    events: Pin<Box<S>>,
+    keep_alive: Option<Duration>,
+    /// Created on the first poll, to not require the runtime on the construction.
+    timer: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S: Stream<Item = Event>> Stream for SseStream<S> {
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        let interval = match this.keep_alive {
+            Some(interval) => interval,
+            None => {
+                return this
+                    .events
+                    .as_mut()
+                    .poll_next(cx)
+                    .map(|event| event.map(|event| Ok(event.encode())))
+            }
+        };
+        let timer = this.timer.get_or_insert_with(|| Box::pin(sleep(interval)));
+
+        match this.events.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                timer.as_mut().reset(Instant::now() + interval);
+                Poll::Ready(Some(Ok(event.encode())))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => {
+                if timer.as_mut().poll(cx).is_ready() {
+                    timer.as_mut().reset(Instant::now() + interval);
+                    Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n"))))
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use futures_channel::mpsc;
+
+    use super::*;
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    fn encoded(event: Event) -> String {
+        String::from_utf8(event.encode().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn event_fields() {
+        let event = Event::default()
+            .comment("hello")
+            .id("7")
+            .event("update")
+            .retry(Duration::from_secs(3))
+            .data("first\nsecond\r\nthird");
+        assert_eq!(
+            encoded(event),
+            ": hello\nid: 7\nevent: update\nretry: 3000\n\
+             data: first\ndata: second\ndata: third\n\n"
+        );
+        assert_eq!(encoded(Event::default().data("")), "data: \n\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn id_with_newline() {
+        let _ = Event::default().id("a\nb");
+    }
+
+    #[tokio::test]
+    async fn keep_alive() {
+        let (tx, rx) = mpsc::unbounded();
+        let mut stream = SseStream {
+            events: Box::pin(rx),
+            keep_alive: Some(Duration::from_millis(10)),
+            timer: None,
+        };
+
+        assert_eq!(next(&mut stream).await.unwrap().unwrap(), ":\n\n");
+
+        tx.unbounded_send(Event::default().data("a")).unwrap();
+        assert_eq!(next(&mut stream).await.unwrap().unwrap(), "data: a\n\n");
+
+        drop(tx);
+        assert!(next(&mut stream).await.is_none());
+    }
+}
@@ -0,0 +1,62 @@
+//! Metadata of the `#[api]` handlers, returned by the generated `routes()` method
+//! and attached to the requests the generated server routes.
+//!
+//! ```ignore
+//! for route in <MyPets as Pets>::routes() {
+//!     println!("{} {} => {}", route.method, route.path, route.handler);
+//! }
+//! ```
+
+use http::Method;
+
+/// Handler the generated server matched,
+/// inserted into both the request and the response extensions.
+///
+/// The template is a low-cardinality label for the metrics and logs, unlike the raw path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MatchedRoute {
+    /// Path template as written in the attribute, like `/pets/{id}`.
+    pub template: &'static str,
+    /// Name of the handler method.
+    pub handler: &'static str,
+}
+
+/// Handler of the `#[api]` trait.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    /// HTTP method, which is `GET` for the WebSocket handlers.
+    pub method: Method,
+    /// Path template as written in the attribute, like `/pets/{id}`.
+    pub path: &'static str,
+    /// Name of the handler method.
+    pub handler: &'static str,
+    /// Declared with `#[websocket]` instead of the HTTP method attribute.
+    pub websocket: bool,
+    /// API version from `#[get("/path", version = "2")]`.
+    pub version: Option<&'static str>,
+    pub params: &'static [ParamInfo],
+    /// Doc comment of the handler method, empty if it has none.
+    pub doc: &'static str,
+}
+
+/// Parameter of the handler, except the `self`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub source: ParamSource,
+    /// Type as written in the method signature, like `Option<String>`.
+    pub ty: &'static str,
+}
+
+/// Where the handler parameter is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamSource {
+    Path,
+    /// Label of the `#[api(host = ...)]` pattern.
+    Host,
+    Query,
+    /// Header of the name.
+    Header(&'static str),
+    Body,
+    WebSocket,
+}
@@ -0,0 +1,410 @@
+//! Runtime router for the handlers which aren't declared in the `#[api]` trait.
+//!
+//! Routes take the same path templates as the `#[api]` handlers,
+//! and are matched in the order they're registered.
+//!
+//! ```ignore
+//! let router = Router::new()
+//!     .route(Method::GET, "/pets/{id}", |_request, params: PathParams| async move {
+//!         let id: u32 = params.parse("id")?;
+//!         Ok::<_, InvalidParam>(format!("pet {}", id))
+//!     });
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use http::{header, Method, Request, StatusCode};
+use http_body::Body as HttpBody;
+
+use crate::rejection::{BoxRequest, InvalidPath, MethodNotAllowed, NotFound, Rejection};
+use crate::request::{self, Param};
+use crate::response::{self, Response, CONTENT_TYPE_TEXT};
+use crate::server::{Options, RequestPath, ServeResult, Server};
+use crate::BoxError;
+
+type BoxHandler = Arc<dyn Fn(BoxRequest, PathParams) -> ServeResult + Send + Sync>;
+type RenderRejection =
+    Arc<dyn Fn(Rejection) -> Result<http::Response<response::Body>, BoxError> + Send + Sync>;
+
+/// Server which dispatches the requests to the registered handlers.
+#[derive(Clone, Default)]
+pub struct Router {
+    inner: Arc<Inner>,
+}
+
+#[derive(Clone, Default)]
+struct Inner {
+    routes: Vec<Route>,
+    fallback: Option<BoxHandler>,
+    render_rejection: Option<RenderRejection>,
+}
+
+#[derive(Clone)]
+struct Route {
+    method: Method,
+    template: String,
+    /// Decoded segments, where `None` is the parameter.
+    path: Vec<Option<String>>,
+    params: Vec<(usize, String)>,
+    trailing_slash: bool,
+    handler: BoxHandler,
+}
+
+/// Path parameters of the matched route, like the `id` of the `/pets/{id}`.
+#[derive(Debug, Clone, Default)]
+pub struct PathParams {
+    params: Vec<(String, String)>,
+}
+
+/// 400 Bad Request - Invalid path parameter
+///
+/// The error of the [`PathParams::parse()`](PathParams::parse),
+/// rendered just like the [`InvalidPathParam`](crate::rejection::InvalidPathParam) rejection.
+#[derive(Debug, thiserror::Error)]
+#[error("400 Bad Request - Invalid path parameter {name}: {error}")]
+pub struct InvalidParam {
+    pub name: String,
+    pub error: BoxError,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler for the method and the path template like `/pets/{id}`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the template doesn't start with `/`,
+    /// or contains empty, `.` or `..` segments.
+    pub fn route<H, F, R>(mut self, method: Method, template: &str, handler: H) -> Self
+    where
+        H: Fn(BoxRequest, PathParams) -> F + Send + Sync + 'static,
+        F: Future<Output = R> + Send + 'static,
+        R: Response,
+    {
+        let parsed = match RequestPath::parse(template) {
+            Ok(parsed) => parsed,
+            Err(err) => panic!("invalid route path {}: {}", template, err),
+        };
+
+        let mut params = vec![];
+        let path = parsed
+            .segments
+            .into_iter()
+            .enumerate()
+            .map(
+                |(idx, seg)| match seg.strip_prefix('{').and_then(|seg| seg.strip_suffix('}')) {
+                    Some(name) => {
+                        params.push((idx, name.to_owned()));
+                        None
+                    }
+                    None => Some(seg),
+                },
+            )
+            .collect();
+
+        let handler: BoxHandler = Arc::new(move |request, params| {
+            let head = request::clone_head(&request);
+            let fut = handler(request, params);
+            Box::pin(async move { fut.await.into_response_for(&head) })
+        });
+
+        self.inner_mut().routes.push(Route {
+            method,
+            template: template.to_owned(),
+            path,
+            params,
+            trailing_slash: parsed.trailing_slash,
+            handler,
+        });
+        self
+    }
+
+    /// Answers the requests no route matches, instead of the `404 Not Found`.
+    pub fn fallback<H, F, R>(mut self, handler: H) -> Self
+    where
+        H: Fn(BoxRequest) -> F + Send + Sync + 'static,
+        F: Future<Output = R> + Send + 'static,
+        R: Response,
+    {
+        self.inner_mut().fallback = Some(Arc::new(move |request, _| {
+            let head = request::clone_head(&request);
+            let fut = handler(request);
+            Box::pin(async move { fut.await.into_response_for(&head) })
+        }));
+        self
+    }
+
+    /// Renders the rejections of the router itself, like the `render_rejection()`
+    /// of the `#[api]` trait.
+    ///
+    /// The router rejects the request with an invalid path,
+    /// the `405 Method Not Allowed` and the `404 Not Found` without the fallback.
+    pub fn render_rejection<F>(mut self, render: F) -> Self
+    where
+        F: Fn(Rejection) -> Result<http::Response<response::Body>, BoxError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.inner_mut().render_rejection = Some(Arc::new(render));
+        self
+    }
+
+    fn reject(&self, rejection: Rejection) -> ServeResult {
+        let resp = match &self.inner.render_rejection {
+            Some(render) => render(rejection),
+            None => rejection.into_response(),
+        };
+        Box::pin(async move { resp })
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::make_mut(&mut self.inner)
+    }
+}
+
+impl Server for Router {
+    fn serve<B>(self, request: Request<B>) -> ServeResult
+    where
+        B: HttpBody + Send + Sync + 'static,
+        B::Error: Into<BoxError>,
+    {
+        let (parts, body) = request.into_parts();
+        let request = Request::from_parts(parts, request::boxed(body));
+
+        let path = match RequestPath::parse(request.uri().path()) {
+            Ok(path) => path,
+            Err(error) => return self.reject(InvalidPath { request, error }.into()),
+        };
+        let inner = &self.inner;
+
+        let route = inner
+            .routes
+            .iter()
+            .filter(|route| route.matches(&path))
+            .find(|route| route.method == request.method());
+        if let Some(route) = route {
+            return (route.handler)(request, route.params(&path));
+        }
+
+        let mut allow = vec![];
+        for route in inner.routes.iter().filter(|route| route.matches(&path)) {
+            let mut methods = vec![route.method.clone()];
+            if route.method == Method::GET {
+                // answered by the GET handler without the body
+                methods.push(Method::HEAD);
+            }
+            for method in methods {
+                if !allow.contains(&method) {
+                    allow.push(method);
+                }
+            }
+        }
+        if !allow.is_empty() && !allow.contains(&Method::OPTIONS) {
+            allow.push(Method::OPTIONS);
+        }
+
+        if request.method() == Method::HEAD && allow.contains(&Method::HEAD) {
+            let mut request = request;
+            *request.method_mut() = Method::GET;
+            let fut = self.serve(request);
+            return Box::pin(async move {
+                // keeps the headers including the Content-Length
+                let (parts, _) = fut.await?.into_parts();
+                Ok(http::Response::from_parts(parts, response::Body::empty()))
+            });
+        }
+        if request.method() == Method::OPTIONS && !allow.is_empty() {
+            return Box::pin(async move { Options { allow }.into_response() });
+        }
+        if !allow.is_empty() {
+            return self.reject(MethodNotAllowed { request, allow }.into());
+        }
+
+        match &inner.fallback {
+            Some(fallback) => fallback(request, PathParams::default()),
+            None => self.reject(NotFound { request }.into()),
+        }
+    }
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field(
+                "routes",
+                &self
+                    .inner
+                    .routes
+                    .iter()
+                    .map(|route| format!("{} {}", route.method, route.template))
+                    .collect::<Vec<_>>(),
+            )
+            .field("fallback", &self.inner.fallback.is_some())
+            .field("render_rejection", &self.inner.render_rejection.is_some())
+            .finish()
+    }
+}
+
+impl Route {
+    fn matches(&self, path: &RequestPath) -> bool {
+        path.segments.len() == self.path.len()
+            && path.trailing_slash == self.trailing_slash
+            && self
+                .path
+                .iter()
+                .zip(&path.segments)
+                .all(|(expected, seg)| expected.as_ref().is_none_or(|expected| expected == seg))
+    }
+
+    fn params(&self, path: &RequestPath) -> PathParams {
+        PathParams {
+            params: self
+                .params
+                .iter()
+                .map(|(idx, name)| (name.clone(), path.segments[*idx].clone()))
+                .collect(),
+        }
+    }
+}
+
+impl PathParams {
+    /// Decoded value of the parameter.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| &**value)
+    }
+
+    /// Parses the parameter just like the `#[api]` handler's path parameter.
+    pub fn parse<T: Param>(&self, name: &str) -> Result<T, InvalidParam> {
+        let error = match self.get(name) {
+            Some(value) => match T::from_param(value) {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            },
+            None => "missing parameter".into(),
+        };
+
+        Err(InvalidParam {
+            name: name.to_owned(),
+            error,
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(name, value)| (&**name, &**value))
+    }
+}
+
+impl Response for InvalidParam {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::BAD_REQUEST]
+    }
+
+    fn into_response(self) -> Result<http::Response<response::Body>, BoxError> {
+        let resp = self.to_string();
+
+        http::Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+            .header(header::CONTENT_LENGTH, resp.len())
+            .body(response::Body::once(resp))
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    async fn call(router: &Router, method: Method, uri: &str) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(http_body::Empty::<Bytes>::new())
+            .unwrap();
+        let resp = router.clone().serve(request).await.unwrap();
+        let status = resp.status();
+        let mut body = resp.into_body();
+        let mut buf = vec![];
+        while let Some(data) = body.data().await {
+            buf.extend_from_slice(&data.unwrap());
+        }
+        (status, String::from_utf8(buf).unwrap())
+    }
+
+    fn router() -> Router {
+        Router::new().route(
+            Method::GET,
+            "/pets/{id}",
+            |_, params: PathParams| async move {
+                let id: u32 = params.parse("id")?;
+                Ok::<_, InvalidParam>(format!("pet {}", id))
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn route_params() {
+        let router = router();
+        assert_eq!(
+            call(&router, Method::GET, "/pets/3").await,
+            (StatusCode::OK, "pet 3".to_owned())
+        );
+        assert_eq!(
+            call(&router, Method::GET, "/pets/x").await.0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            call(&router, Method::DELETE, "/pets/3").await.0,
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            call(&router, Method::GET, "/pets/3/toys").await.0,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn render_rejection() {
+        let router = router().render_rejection(|rejection| {
+            let status = rejection.status();
+            let mut resp = format!("custom {}", status.as_u16()).into_response()?;
+            *resp.status_mut() = status;
+            Ok(resp)
+        });
+        assert_eq!(
+            call(&router, Method::GET, "/pets/../3").await,
+            (StatusCode::BAD_REQUEST, "custom 400".to_owned())
+        );
+        assert_eq!(
+            call(&router, Method::DELETE, "/pets/3").await,
+            (StatusCode::METHOD_NOT_ALLOWED, "custom 405".to_owned())
+        );
+        assert_eq!(
+            call(&router, Method::GET, "/nope").await,
+            (StatusCode::NOT_FOUND, "custom 404".to_owned())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn fallback_negotiates() {
+        let router = router().fallback(|_| async { crate::Negotiated(vec![1, 2]) });
+        let request = Request::get("/nope")
+            .header(header::ACCEPT, "text/html")
+            .body(http_body::Empty::<Bytes>::new())
+            .unwrap();
+        let resp = router.serve(request).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+}
@@ -4,15 +4,25 @@ use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use http::{header, Request, Response, StatusCode};
+use http::{header, Method, Request, Response, StatusCode};
 use http_body::Body as HttpBody;
 
+use crate::cors::{Cors, CorsConfig};
 use crate::response;
 use crate::BoxError;
 
+mod host;
+mod layer;
+mod path;
+mod version;
 #[cfg(feature = "hyper")]
 mod with_hyper;
 
+pub use host::{request_host, Hosts};
+pub use layer::{Handler, HandlerService, Layered};
+pub use path::RequestPath;
+pub use version::Versioning;
+
 #[cfg(feature = "hyper")]
 pub use with_hyper::Hyper;
 
@@ -20,12 +30,19 @@ pub type ServeResult =
     Pin<Box<dyn Future<Output = Result<Response<response::Body>, BoxError>> + Send + 'static>>;
 
 pub trait Server: Clone + Send + 'static {
-    fn serve<B: HttpBody>(self, request: Request<B>) -> ServeResult;
+    fn serve<B>(self, request: Request<B>) -> ServeResult
+    where
+        B: HttpBody + Send + Sync + 'static,
+        B::Error: Into<BoxError>;
 
     fn into_service(self) -> Service<Self> {
         Service(self)
     }
 
+    fn cors(self, config: CorsConfig) -> Cors<Self> {
+        Cors::new(self, config)
+    }
+
     #[cfg(feature = "hyper")]
     fn bind(self, addr: SocketAddr) -> hyper::Result<Hyper<Self>> {
         Hyper::bind(self, addr)
@@ -40,10 +57,28 @@ pub trait Server: Clone + Send + 'static {
 #[derive(Debug, Clone)]
 pub struct Service<S: Server>(S);
 
+/// `204 No Content` answering the `OPTIONS` request with the `Allow` header.
+///
+/// The generated server answers it for every routed path,
+/// unless the trait defines an explicit `#[options]` handler.
 #[derive(Debug)]
-pub struct NotFound;
+pub struct Options {
+    pub allow: Vec<Method>,
+}
 
-impl<S: Server, B: HttpBody> tower::Service<Request<B>> for Service<S> {
+/// `308 Permanent Redirect` to the path with or without the trailing slash,
+/// answered by the generated server with the `trailing_slash = "redirect"` policy.
+#[derive(Debug)]
+pub struct Redirect {
+    pub location: String,
+}
+
+impl<S, B> tower::Service<Request<B>> for Service<S>
+where
+    S: Server,
+    B: HttpBody + Send + Sync + 'static,
+    B::Error: Into<BoxError>,
+{
     type Response = Response<response::Body>;
     type Error = BoxError;
     type Future = ServeResult;
@@ -57,15 +92,32 @@ impl<S: Server, B: HttpBody> tower::Service<Request<B>> for Service<S> {
     }
 }
 
-impl crate::response::Response for NotFound {
+impl response::Response for Options {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::NO_CONTENT]
+    }
+
     fn into_response(self) -> Result<Response<response::Body>, BoxError> {
-        let resp = "404 Not Found";
+        let allow: Vec<_> = self.allow.iter().map(Method::as_str).collect();
 
         Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .header(header::CONTENT_TYPE, crate::response::CONTENT_TYPE_TEXT)
-            .header(header::CONTENT_LENGTH, resp.len())
-            .body(response::Body::once(resp))
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ALLOW, allow.join(", "))
+            .body(response::Body::empty())
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+impl response::Response for Redirect {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::PERMANENT_REDIRECT]
+    }
+
+    fn into_response(self) -> Result<Response<response::Body>, BoxError> {
+        Response::builder()
+            .status(StatusCode::PERMANENT_REDIRECT)
+            .header(header::LOCATION, self.location)
+            .body(response::Body::empty())
             .map_err(|err| Box::new(err) as _)
     }
 }
@@ -0,0 +1,242 @@
+use std::fmt;
+use std::sync::Arc;
+
+use http::request::Parts;
+use http::uri::Authority;
+use http::{header, Request};
+use http_body::Body as HttpBody;
+
+use crate::rejection::{BoxRequest, NotFound};
+use crate::request;
+use crate::response::Response;
+use crate::BoxError;
+
+use super::{ServeResult, Server};
+
+type BoxServer = Arc<dyn Fn(BoxRequest) -> ServeResult + Send + Sync>;
+
+/// Server which dispatches the request to one of the servers by its host.
+///
+/// The first server whose pattern matches takes the request,
+/// and the requests of unknown hosts are answered with `404 Not Found`.
+///
+/// ```ignore
+/// let server = Hosts::new()
+///     .host("api.example.com", ApiServer(api))
+///     .host("{tenant}.example.com", TenantServer(tenant));
+/// ```
+#[derive(Clone, Default)]
+pub struct Hosts {
+    routes: Arc<Vec<(HostPattern, BoxServer)>>,
+}
+
+/// Host like `api.example.com`, where the `{name}` label matches any single label.
+#[derive(Debug, Clone)]
+struct HostPattern {
+    raw: String,
+    labels: Vec<Option<String>>,
+}
+
+/// Host of the request without the port, lowercased and without the trailing dot.
+///
+/// It's taken from the URI for the HTTP/2 `:authority` and the absolute-form requests,
+/// and from the `Host` header otherwise.
+pub fn request_host(parts: &Parts) -> Option<String> {
+    let host = match parts.uri.host() {
+        Some(host) => host.to_owned(),
+        None => {
+            let host = parts.headers.get(header::HOST)?.to_str().ok()?;
+            host.parse::<Authority>().ok()?.host().to_owned()
+        }
+    };
+    let host = host.strip_suffix('.').unwrap_or(&host);
+
+    Some(host.to_ascii_lowercase())
+}
+
+impl Hosts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the server for the host pattern like `api.example.com` or `{tenant}.example.com`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern contains an empty label or a label with invalid characters.
+    pub fn host<S: Server + Sync>(mut self, pattern: &str, server: S) -> Self {
+        let pattern = match HostPattern::new(pattern) {
+            Ok(pattern) => pattern,
+            Err(err) => panic!("invalid host pattern {}: {}", pattern, err),
+        };
+        let serve: BoxServer = Arc::new(move |request| server.clone().serve(request));
+        Arc::make_mut(&mut self.routes).push((pattern, serve));
+        self
+    }
+}
+
+impl Server for Hosts {
+    fn serve<B>(self, request: Request<B>) -> ServeResult
+    where
+        B: HttpBody + Send + Sync + 'static,
+        B::Error: Into<BoxError>,
+    {
+        let (parts, body) = request.into_parts();
+        let host = request_host(&parts);
+        let request = Request::from_parts(parts, request::boxed(body));
+
+        let route = host.and_then(|host| {
+            self.routes
+                .iter()
+                .find(|(pattern, _)| pattern.matches(&host))
+        });
+        match route {
+            Some((_, serve)) => serve(request),
+            None => Box::pin(async move { NotFound { request }.into_response() }),
+        }
+    }
+}
+
+impl fmt::Debug for Hosts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.routes.iter().map(|(pattern, _)| &pattern.raw))
+            .finish()
+    }
+}
+
+impl HostPattern {
+    fn new(pattern: &str) -> Result<Self, BoxError> {
+        let labels = pattern
+            .split('.')
+            .map(
+                |label| match label.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+                    Some("") => Err("host pattern contains an empty parameter name".into()),
+                    Some(_) => Ok(None),
+                    None if is_label(label) => Ok(Some(label.to_ascii_lowercase())),
+                    None if label.is_empty() => Err("host pattern contains an empty label".into()),
+                    None => Err(format!("invalid host label `{}`", label).into()),
+                },
+            )
+            .collect::<Result<_, BoxError>>()?;
+
+        Ok(HostPattern {
+            raw: pattern.to_owned(),
+            labels,
+        })
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        let labels: Vec<_> = host.split('.').collect();
+
+        labels.len() == self.labels.len()
+            && self
+                .labels
+                .iter()
+                .zip(labels)
+                .all(|(pattern, label)| match pattern {
+                    Some(pattern) => pattern == label,
+                    None => !label.is_empty(),
+                })
+    }
+}
+
+fn is_label(label: &str) -> bool {
+    !label.is_empty()
+        && label
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+
+    /// Server which answers with its name.
+    #[derive(Clone)]
+    struct Named(&'static str);
+
+    impl Server for Named {
+        fn serve<B>(self, _request: Request<B>) -> ServeResult
+        where
+            B: HttpBody + Send + Sync + 'static,
+            B::Error: Into<BoxError>,
+        {
+            Box::pin(async move { self.0.into_response() })
+        }
+    }
+
+    fn parts(uri: &str, host: Option<&str>) -> Parts {
+        let mut request = Request::get(uri);
+        if let Some(host) = host {
+            request = request.header(header::HOST, host);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn request_host_normalizes() {
+        let host = |uri, host| request_host(&parts(uri, host));
+
+        assert_eq!(
+            host("/", Some("API.Example.com:8080")).unwrap(),
+            "api.example.com"
+        );
+        assert_eq!(host("/", Some("example.com.")).unwrap(), "example.com");
+        // the absolute-form URI takes precedence over the header
+        assert_eq!(
+            host("http://Uri.example.com:80/", Some("header.example.com")).unwrap(),
+            "uri.example.com"
+        );
+        assert_eq!(host("/", None), None);
+        assert_eq!(host("/", Some("bad host")), None);
+    }
+
+    #[test]
+    fn host_pattern() {
+        let pattern = HostPattern::new("{tenant}.Example.com").unwrap();
+        assert!(pattern.matches("acme.example.com"));
+        assert!(!pattern.matches(".example.com"));
+        assert!(!pattern.matches("example.com"));
+        assert!(!pattern.matches("a.b.example.com"));
+
+        for invalid in ["", "api..example.com", "{}.example.com", "api.ex ample.com"] {
+            assert!(HostPattern::new(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    async fn serve(hosts: &Hosts, host: &str) -> (StatusCode, String) {
+        let request = Request::get("/")
+            .header(header::HOST, host)
+            .body(http_body::Empty::<bytes::Bytes>::new())
+            .unwrap();
+        let resp = hosts.clone().serve(request).await.unwrap();
+        let status = resp.status();
+
+        let mut body = resp.into_body();
+        let mut buf = vec![];
+        while let Some(data) = body.data().await {
+            buf.extend_from_slice(&data.unwrap());
+        }
+        (status, String::from_utf8(buf).unwrap())
+    }
+
+    #[tokio::test]
+    async fn hosts_dispatch() {
+        let hosts = Hosts::new()
+            .host("api.example.com", Named("api"))
+            .host("{tenant}.example.com", Named("tenant"));
+
+        assert_eq!(serve(&hosts, "API.example.com").await.1, "api");
+        assert_eq!(serve(&hosts, "acme.example.com:443").await.1, "tenant");
+        assert_eq!(serve(&hosts, "example.org").await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    #[should_panic]
+    fn hosts_reject_empty_label() {
+        let _ = Hosts::new().host("api..example.com", Named("api"));
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{ready, Context, Poll};
+
+use http::{header, Response, StatusCode};
+use pin_project::pin_project;
+
+use crate::rejection::BoxRequest;
+use crate::response::{self, CONTENT_TYPE_TEXT};
+use crate::BoxError;
+
+use super::ServeResult;
+
+/// Handler the request is routed to, carried in the request extensions through the layers.
+///
+/// It keeps the path and host parameters parsed before the layers,
+/// and clones them on every call so the layers can call the handler more than once.
+/// They should be `Clone` for the handlers with the `#[layer(...)]`.
+#[derive(Clone)]
+pub struct Handler(Arc<dyn Fn(BoxRequest) -> ServeResult + Send + Sync>);
+
+/// Innermost service of the stack the `#[layer(...)]` attributes build,
+/// which calls the [`Handler`](Handler) of the request.
+///
+/// The stack doesn't depend on the server, so the generated server builds it only once
+/// for each handler and shares it between the requests.
+/// It's shared by every instance of the server in the process as well,
+/// so a `ConcurrencyLimitLayer` limits the handler across all of them,
+/// and the layers can't take the state of the server.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HandlerService;
+
+/// Type erased stack of the layers, used by the generated server.
+pub struct Layered {
+    service: Mutex<Box<dyn CloneService>>,
+}
+
+/// Error returned by the handler itself, rather than the layers.
+#[derive(Debug)]
+struct HandlerError(BoxError);
+
+trait CloneService: Send {
+    fn clone_box(&self) -> Box<dyn CloneService>;
+
+    fn call_box(self: Box<Self>, request: BoxRequest) -> ServeResult;
+}
+
+impl Handler {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(BoxRequest) -> ServeResult + Send + Sync + 'static,
+    {
+        Handler(Arc::new(handler))
+    }
+}
+
+impl fmt::Debug for Handler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handler").finish_non_exhaustive()
+    }
+}
+
+impl tower::Service<BoxRequest> for HandlerService {
+    type Response = Response<response::Body>;
+    type Error = BoxError;
+    type Future = ServeResult;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: BoxRequest) -> Self::Future {
+        let handler = match request.extensions().get::<Handler>() {
+            Some(handler) => handler.clone(),
+            None => {
+                return Box::pin(async {
+                    Err("the layer dropped the handler from the request extensions".into())
+                })
+            }
+        };
+
+        let fut = (handler.0)(request);
+        Box::pin(async move { fut.await.map_err(|err| Box::new(HandlerError(err)) as _) })
+    }
+}
+
+impl Layered {
+    /// Erases the type of the layered [`HandlerService`](HandlerService).
+    ///
+    /// The service is cloned for every request like the other tower servers do,
+    /// so the layers which share their state between the clones,
+    /// like the `ConcurrencyLimit`, limit every request to the handler.
+    pub fn new<S>(service: S) -> Self
+    where
+        S: tower::Service<BoxRequest, Response = Response<response::Body>> + Clone + Send + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send,
+    {
+        Layered {
+            service: Mutex::new(Box::new(service)),
+        }
+    }
+
+    /// Calls the handler through the layers.
+    ///
+    /// Errors of the layers, like the timeout or the load shedding,
+    /// are answered as the `503 Service Unavailable`,
+    /// and errors of the handler are returned as is.
+    pub fn call(&self, request: BoxRequest) -> ServeResult {
+        let service = self
+            .service
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone_box();
+        let fut = service.call_box(request);
+
+        Box::pin(async move {
+            match fut.await {
+                Ok(resp) => Ok(resp),
+                Err(err) => match err.downcast::<HandlerError>() {
+                    Ok(err) => Err(err.0),
+                    Err(err) => service_unavailable(err),
+                },
+            }
+        })
+    }
+}
+
+impl fmt::Debug for Layered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Layered").finish_non_exhaustive()
+    }
+}
+
+impl<S> CloneService for S
+where
+    S: tower::Service<BoxRequest, Response = Response<response::Body>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    fn clone_box(&self) -> Box<dyn CloneService> {
+        Box::new(self.clone())
+    }
+
+    fn call_box(self: Box<Self>, request: BoxRequest) -> ServeResult {
+        // not an `async` block, which can't prove the `S::Future` is `Send`
+        // for the boxed error in the `BoxRequest`
+        Box::pin(CallService {
+            service: *self,
+            request: Some(request),
+            future: None,
+        })
+    }
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for HandlerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+fn service_unavailable(err: BoxError) -> Result<Response<response::Body>, BoxError> {
+    let resp = format!("503 Service Unavailable - {}", err);
+
+    Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+        .header(header::CONTENT_LENGTH, resp.len())
+        .body(response::Body::once(resp))
+        .map_err(|err| Box::new(err) as _)
+}
+
+/// Calls the service once it's ready.
+#[pin_project]
+struct CallService<S: tower::Service<BoxRequest>> {
+    service: S,
+    request: Option<BoxRequest>,
+    #[pin]
+    future: Option<S::Future>,
+}
+
+impl<S> Future for CallService<S>
+where
+    S: tower::Service<BoxRequest, Response = Response<response::Body>>,
+    S::Error: Into<BoxError>,
+{
+    type Output = Result<Response<response::Body>, BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(future) = this.future.as_mut().as_pin_mut() {
+                return future.poll(cx).map_err(Into::into);
+            }
+
+            if let Err(err) = ready!(this.service.poll_ready(cx)) {
+                return Poll::Ready(Err(err.into()));
+            }
+            let request = this
+                .request
+                .take()
+                .expect("CallService polled after completion");
+            this.future.set(Some(this.service.call(request)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tower::Service;
+
+    use super::*;
+
+    fn request(handler: Option<Handler>) -> BoxRequest {
+        let mut request = http::Request::new(crate::request::boxed(
+            http_body::Empty::<bytes::Bytes>::new(),
+        ));
+        if let Some(handler) = handler {
+            request.extensions_mut().insert(handler);
+        }
+        request
+    }
+
+    /// Layer service which always fails.
+    #[derive(Clone)]
+    struct Overloaded;
+
+    impl tower::Service<BoxRequest> for Overloaded {
+        type Response = Response<response::Body>;
+        type Error = BoxError;
+        type Future = ServeResult;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Err("service overloaded".into()))
+        }
+
+        fn call(&mut self, _request: BoxRequest) -> Self::Future {
+            unreachable!("never ready")
+        }
+    }
+
+    #[tokio::test]
+    async fn handler_service_calls_handler_again() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = {
+            let calls = Arc::clone(&calls);
+            Handler::new(move |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Ok(Response::new(response::Body::empty())) })
+            })
+        };
+
+        let mut service = HandlerService;
+        service.call(request(Some(handler.clone()))).await.unwrap();
+        service.call(request(Some(handler))).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        assert!(service.call(request(None)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn layer_error_is_service_unavailable() {
+        let resp = Layered::new(Overloaded).call(request(None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn handler_error_is_returned() {
+        let handler = Handler::new(|_| Box::pin(async { Err("handler failed".into()) }));
+        let err = Layered::new(HandlerService)
+            .call(request(Some(handler)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "handler failed");
+    }
+}
@@ -0,0 +1,210 @@
+use http::uri::Uri;
+
+use crate::BoxError;
+
+/// Request path split into the percent-decoded segments, used by the generated server.
+///
+/// The root path `/` has no segment, and the trailing slash is not a segment either.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestPath {
+    pub segments: Vec<String>,
+    pub trailing_slash: bool,
+}
+
+impl RequestPath {
+    /// Splits and decodes the path.
+    ///
+    /// Paths with the empty, `.` or `..` segments are rejected,
+    /// as well as the segments which aren't valid UTF-8 after decoding.
+    pub fn parse(path: &str) -> Result<Self, BoxError> {
+        let path = path
+            .strip_prefix('/')
+            .ok_or_else(|| format!("path should start with `/`, found `{}`", path))?;
+        if path.is_empty() {
+            return Ok(RequestPath {
+                segments: vec![],
+                trailing_slash: false,
+            });
+        }
+
+        let (path, trailing_slash) = match path.strip_suffix('/') {
+            Some(path) => (path, true),
+            None => (path, false),
+        };
+
+        let segments = path
+            .split('/')
+            .map(|seg| {
+                let seg = percent_decode(seg)?;
+                check_segment(&seg)?;
+                Ok(seg)
+            })
+            .collect::<Result<_, BoxError>>()?;
+
+        Ok(RequestPath {
+            segments,
+            trailing_slash,
+        })
+    }
+
+    /// Joins the percent-encoded segments back into the URI, the reverse of the [`parse()`](Self::parse).
+    ///
+    /// The query should be already encoded, and the empty one is omitted.
+    /// Bytes not allowed in the query are percent-encoded as well.
+    ///
+    /// It fails if a segment is empty, `.` or `..`, which the `parse()` rejects,
+    /// or the URI is too long.
+    pub fn to_uri(&self, query: Option<&str>) -> Result<Uri, BoxError> {
+        let mut uri = String::new();
+        for seg in &self.segments {
+            check_segment(seg)?;
+            uri.push('/');
+            percent_encode(seg, &mut uri);
+        }
+        if self.segments.is_empty() || self.trailing_slash {
+            uri.push('/');
+        }
+        if let Some(query) = query.filter(|query| !query.is_empty()) {
+            uri.push('?');
+            encode_query(query, &mut uri);
+        }
+
+        Ok(uri.parse()?)
+    }
+}
+
+fn check_segment(seg: &str) -> Result<(), BoxError> {
+    match seg {
+        "" => Err("path contains an empty segment".into()),
+        "." | ".." => Err(format!("path contains the `{}` segment", seg).into()),
+        _ => Ok(()),
+    }
+}
+
+/// Encodes every byte except the unreserved characters of the RFC 3986.
+fn percent_encode(seg: &str, buf: &mut String) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    for b in seg.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            buf.push(b as char);
+        } else {
+            buf.push('%');
+            buf.push(HEX[(b >> 4) as usize] as char);
+            buf.push(HEX[(b & 0xf) as usize] as char);
+        }
+    }
+}
+
+/// Encodes the bytes not allowed in the query, keeping the already encoded ones.
+fn encode_query(query: &str, buf: &mut String) {
+    for c in query.chars() {
+        if c.is_ascii_alphanumeric() || "-._~!$&'()*+,;=:@/?%".contains(c) {
+            buf.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            percent_encode(c.encode_utf8(&mut bytes), buf);
+        }
+    }
+}
+
+fn percent_decode(seg: &str) -> Result<String, BoxError> {
+    if !seg.contains('%') {
+        return Ok(seg.to_owned());
+    }
+
+    let mut bytes = Vec::with_capacity(seg.len());
+    let mut iter = seg.bytes();
+
+    while let Some(b) = iter.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+
+        let hex = |b: Option<u8>| b.and_then(|b| (b as char).to_digit(16));
+        match (hex(iter.next()), hex(iter.next())) {
+            (Some(hi), Some(lo)) => bytes.push((hi * 16 + lo) as u8),
+            _ => return Err(format!("invalid percent-encoding in `{}`", seg).into()),
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| format!("invalid UTF-8 in `{}`", seg).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(segments: &[&str], trailing_slash: bool) -> RequestPath {
+        RequestPath {
+            segments: segments.iter().map(|seg| seg.to_string()).collect(),
+            trailing_slash,
+        }
+    }
+
+    #[test]
+    fn parse_decodes_segments() {
+        assert_eq!(RequestPath::parse("/").unwrap(), path(&[], false));
+        assert_eq!(
+            RequestPath::parse("/pets/Tom%20%26%20Jerry%2F%c3%bc").unwrap(),
+            path(&["pets", "Tom & Jerry/ü"], false)
+        );
+        assert_eq!(
+            RequestPath::parse("/pets/a+b/").unwrap(),
+            path(&["pets", "a+b"], true)
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_paths() {
+        for invalid in [
+            "pets",
+            "//pets",
+            "/pets//toys",
+            "/pets/./toys",
+            "/pets/../admin",
+            "/pets/%2E%2E",
+            "/pets/%zz",
+            "/pets/%4",
+            "/pets/%FF",
+        ] {
+            assert!(RequestPath::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn to_uri_encodes_segments() {
+        let uri = path(&["pets", "Tom & Jerry/ü"], false)
+            .to_uri(None)
+            .unwrap();
+        assert_eq!(uri, "/pets/Tom%20%26%20Jerry%2F%C3%BC");
+        assert_eq!(path(&[], false).to_uri(Some("")).unwrap(), "/");
+        assert_eq!(path(&["pets"], true).to_uri(None).unwrap(), "/pets/");
+    }
+
+    #[test]
+    fn to_uri_encodes_query() {
+        let uri = path(&["pets"], false)
+            .to_uri(Some("q=a+b%26c&tag=<ü> \"#"))
+            .unwrap();
+        assert_eq!(uri, "/pets?q=a+b%26c&tag=%3C%C3%BC%3E%20%22%23");
+    }
+
+    #[test]
+    fn to_uri_rejects_dot_segments() {
+        for invalid in ["", ".", ".."] {
+            assert!(path(&["pets", invalid], false).to_uri(None).is_err());
+        }
+        assert_eq!(
+            path(&["pets", "..."], false).to_uri(None).unwrap(),
+            "/pets/..."
+        );
+    }
+
+    #[test]
+    fn to_uri_too_long() {
+        let long = "a".repeat(u16::MAX as usize);
+        assert!(path(&[&long], false).to_uri(None).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use http::header::{self, HeaderMap, HeaderName};
+
+const ACCEPT_VERSION: HeaderName = HeaderName::from_static("accept-version");
+
+/// Where the generated server reads the requested API version from,
+/// set by `#[api(server(Name, versioning = ...))]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Versioning<'a> {
+    /// Path prefix like `/v2/pets`.
+    Path,
+    /// `Accept-Version: 2` header.
+    Header,
+    /// Vendor media type like `application/vnd.acme.v2+json` in the `Accept` header.
+    MediaType { vendor: &'a str },
+}
+
+impl Versioning<'_> {
+    /// Takes the requested version, removing its prefix from the path segments for the `Path`.
+    pub fn request_version(self, headers: &HeaderMap, path: &mut Vec<String>) -> Option<String> {
+        match self {
+            Versioning::Path => {
+                let version = path.first()?.strip_prefix('v')?;
+                // not a version prefix, like `/videos`
+                if !version.starts_with(|c: char| c.is_ascii_digit()) {
+                    return None;
+                }
+                let version = version.to_owned();
+                path.remove(0);
+                Some(version)
+            }
+            Versioning::Header => {
+                let version = headers.get(ACCEPT_VERSION)?.to_str().ok()?.trim();
+                Some(version.to_owned())
+            }
+            Versioning::MediaType { vendor } => headers
+                .get_all(header::ACCEPT)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .find_map(|media_type| vendor_version(media_type, vendor)),
+        }
+    }
+}
+
+/// Takes the `2` from the `application/vnd.acme.v2+json; q=0.9`.
+fn vendor_version(media_type: &str, vendor: &str) -> Option<String> {
+    let media_type = media_type.split(';').next()?.trim().to_ascii_lowercase();
+    let rest = media_type
+        .strip_prefix("application/vnd.")?
+        .strip_prefix(vendor)?
+        .strip_prefix(".v")?;
+    let version = rest.split('+').next()?;
+
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn path(segments: &[&str]) -> Vec<String> {
+        segments.iter().map(|seg| seg.to_string()).collect()
+    }
+
+    #[test]
+    fn path_version() {
+        let headers = HeaderMap::new();
+
+        let mut segments = path(&["v2", "pets"]);
+        let version = Versioning::Path.request_version(&headers, &mut segments);
+        assert_eq!(version.as_deref(), Some("2"));
+        assert_eq!(segments, ["pets"]);
+
+        for unversioned in [&["videos"][..], &["v"], &[]] {
+            let mut segments = path(unversioned);
+            assert_eq!(
+                Versioning::Path.request_version(&headers, &mut segments),
+                None
+            );
+            assert_eq!(segments, unversioned);
+        }
+    }
+
+    #[test]
+    fn header_version() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            Versioning::Header.request_version(&headers, &mut vec![]),
+            None
+        );
+
+        headers.insert(ACCEPT_VERSION, HeaderValue::from_static(" 2 "));
+        let mut segments = path(&["v1"]);
+        let version = Versioning::Header.request_version(&headers, &mut segments);
+        assert_eq!(version.as_deref(), Some("2"));
+        // the path is left as is
+        assert_eq!(segments, ["v1"]);
+    }
+
+    #[test]
+    fn media_type_version() {
+        let versioning = Versioning::MediaType { vendor: "acme" };
+        let version = |accept: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+            versioning.request_version(&headers, &mut vec![])
+        };
+
+        assert_eq!(
+            version("application/vnd.acme.v2+json").as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            version("text/html, Application/Vnd.Acme.V3+json; q=0.9").as_deref(),
+            Some("3")
+        );
+        assert_eq!(version("application/vnd.acme.v4").as_deref(), Some("4"));
+        assert_eq!(version("application/vnd.other.v2+json"), None);
+        assert_eq!(version("application/vnd.acme.v+json"), None);
+        assert_eq!(version("application/json"), None);
+    }
+}
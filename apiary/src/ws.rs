@@ -0,0 +1,341 @@
+//! WebSocket handlers.
+//!
+//! Handlers are declared with `#[websocket("/path")]` in the `#[api]` trait,
+//! and take the [`WebSocket`](WebSocket) as a parameter without any attribute.
+//! Other parameters are extracted from the upgrade request just like the normal handlers.
+//!
+//! ```ignore
+//! #[websocket("/ws/chat/{room}")]
+//! async fn chat(self: Arc<Self>, room: String, socket: apiary::ws::WebSocket);
+//! ```
+
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use futures_core::Stream;
+use futures_sink::Sink;
+use http::request::Parts;
+use http::{header, HeaderMap, HeaderValue, Response as HttpResponse, StatusCode};
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use sha1::{Digest, Sha1};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{self, Role};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::response::{Body, Response, CONTENT_TYPE_TEXT};
+use crate::BoxError;
+
+const WEBSOCKET_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Established WebSocket connection.
+///
+/// Pings are answered automatically, but still returned from the [`recv()`](WebSocket::recv).
+#[derive(Debug)]
+pub struct WebSocket {
+    inner: WebSocketStream<Upgraded>,
+}
+
+/// Single WebSocket message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<CloseFrame>),
+}
+
+/// Status code and the reason of the close message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+/// Validated WebSocket upgrade request, used by the generated server.
+#[derive(Debug)]
+pub struct Upgrade {
+    accept: HeaderValue,
+    on_upgrade: Option<OnUpgrade>,
+}
+
+/// The request is not a valid WebSocket upgrade request.
+#[derive(Debug)]
+pub enum InvalidUpgrade {
+    /// `Connection: upgrade` or `Upgrade: websocket` header is missing.
+    NotUpgrade,
+    /// `Sec-WebSocket-Version` is not 13.
+    UnsupportedVersion,
+    /// `Sec-WebSocket-Key` is missing or malformed.
+    InvalidKey,
+}
+
+impl WebSocket {
+    /// Waits for the next message, returns `None` if the connection is closed.
+    pub async fn recv(&mut self) -> Option<Result<Message, BoxError>> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    pub async fn send(&mut self, message: Message) -> Result<(), BoxError> {
+        let mut inner = Pin::new(&mut self.inner);
+
+        poll_fn(|cx| inner.as_mut().poll_ready(cx)).await?;
+        inner.as_mut().start_send(message.into())?;
+        poll_fn(|cx| inner.as_mut().poll_flush(cx)).await?;
+        Ok(())
+    }
+
+    /// Sends the close message and waits for the connection to be closed.
+    pub async fn close(mut self, frame: Option<CloseFrame>) -> Result<(), BoxError> {
+        self.send(Message::Close(frame)).await?;
+
+        // drains until the close reply from the client
+        while let Some(message) = self.recv().await {
+            message?;
+        }
+        Ok(())
+    }
+}
+
+impl Stream for WebSocket {
+    type Item = Result<Message, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(Box::new(err)))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            // raw frames are never returned while reading
+            if let Some(message) = Message::from_protocol(message) {
+                return Poll::Ready(Some(Ok(message)));
+            }
+        }
+    }
+}
+
+impl Message {
+    fn from_protocol(message: protocol::Message) -> Option<Self> {
+        Some(match message {
+            protocol::Message::Text(text) => Message::Text(text),
+            protocol::Message::Binary(data) => Message::Binary(data),
+            protocol::Message::Ping(data) => Message::Ping(data),
+            protocol::Message::Pong(data) => Message::Pong(data),
+            protocol::Message::Close(frame) => Message::Close(frame.map(|frame| CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.into_owned(),
+            })),
+            protocol::Message::Frame(_) => return None,
+        })
+    }
+}
+
+impl From<Message> for protocol::Message {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::Text(text) => protocol::Message::Text(text),
+            Message::Binary(data) => protocol::Message::Binary(data),
+            Message::Ping(data) => protocol::Message::Ping(data),
+            Message::Pong(data) => protocol::Message::Pong(data),
+            Message::Close(frame) => {
+                protocol::Message::Close(frame.map(|frame| protocol::CloseFrame {
+                    code: CloseCode::from(frame.code),
+                    reason: frame.reason.into(),
+                }))
+            }
+        }
+    }
+}
+
+impl Upgrade {
+    /// Validates the upgrade headers, and takes the connection upgrade from the request.
+    pub fn from_request(request: &mut Parts) -> Result<Self, InvalidUpgrade> {
+        let headers = &request.headers;
+
+        if !has_token(headers, header::CONNECTION, "upgrade")
+            || !has_token(headers, header::UPGRADE, "websocket")
+        {
+            return Err(InvalidUpgrade::NotUpgrade);
+        }
+        if headers.get(header::SEC_WEBSOCKET_VERSION) != Some(&HeaderValue::from_static("13")) {
+            return Err(InvalidUpgrade::UnsupportedVersion);
+        }
+
+        let key = headers
+            .get(header::SEC_WEBSOCKET_KEY)
+            .ok_or(InvalidUpgrade::InvalidKey)?;
+        match BASE64.decode(key.as_bytes()) {
+            Ok(decoded) if decoded.len() == 16 => {}
+            _ => return Err(InvalidUpgrade::InvalidKey),
+        }
+
+        let mut hasher = Sha1::new();
+        hasher.update(key.as_bytes());
+        hasher.update(WEBSOCKET_GUID);
+        let accept = BASE64.encode(hasher.finalize());
+
+        Ok(Upgrade {
+            accept: HeaderValue::from_str(&accept).map_err(|_| InvalidUpgrade::InvalidKey)?,
+            on_upgrade: request.extensions.remove::<OnUpgrade>(),
+        })
+    }
+
+    /// Answers `101 Switching Protocols`, and runs the `handler`
+    /// on its own task once the connection is upgraded.
+    ///
+    /// Fails if the connection doesn't support upgrades,
+    /// which happens when the request doesn't come from the hyper server.
+    pub fn on_upgrade<F, Fut>(self, handler: F) -> Result<HttpResponse<Body>, BoxError>
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let on_upgrade = self
+            .on_upgrade
+            .ok_or("The connection doesn't support the protocol upgrade")?;
+
+        tokio::spawn(async move {
+            // the client is already gone if the upgrade fails
+            if let Ok(upgraded) = on_upgrade.await {
+                let inner = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                handler(WebSocket { inner }).await;
+            }
+        });
+
+        HttpResponse::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, HeaderValue::from_static("upgrade"))
+            .header(header::UPGRADE, HeaderValue::from_static("websocket"))
+            .header(header::SEC_WEBSOCKET_ACCEPT, self.accept)
+            .body(Body::empty())
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+/// Checks whether the comma separated header contains the token.
+fn has_token(headers: &HeaderMap, name: header::HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|t| t.trim().eq_ignore_ascii_case(token))
+}
+
+impl InvalidUpgrade {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            InvalidUpgrade::NotUpgrade | InvalidUpgrade::UnsupportedVersion => {
+                StatusCode::UPGRADE_REQUIRED
+            }
+            InvalidUpgrade::InvalidKey => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl std::fmt::Display for InvalidUpgrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            InvalidUpgrade::NotUpgrade => {
+                "426 Upgrade Required - Expected the WebSocket upgrade request"
+            }
+            InvalidUpgrade::UnsupportedVersion => {
+                "426 Upgrade Required - Unsupported WebSocket version"
+            }
+            InvalidUpgrade::InvalidKey => "400 Bad Request - Invalid Sec-WebSocket-Key header",
+        })
+    }
+}
+
+impl std::error::Error for InvalidUpgrade {}
+
+impl Response for InvalidUpgrade {
+    fn statuses() -> Vec<StatusCode> {
+        vec![StatusCode::UPGRADE_REQUIRED, StatusCode::BAD_REQUEST]
+    }
+
+    fn into_response(self) -> Result<HttpResponse<Body>, BoxError> {
+        let header = match self {
+            InvalidUpgrade::NotUpgrade => (header::UPGRADE, "websocket"),
+            InvalidUpgrade::UnsupportedVersion | InvalidUpgrade::InvalidKey => {
+                (header::SEC_WEBSOCKET_VERSION, "13")
+            }
+        };
+        let resp = self.to_string();
+
+        HttpResponse::builder()
+            .status(self.status())
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+            .header(header::CONTENT_LENGTH, resp.len())
+            .header(header.0, HeaderValue::from_static(header.1))
+            .body(Body::once(resp))
+            .map_err(|err| Box::new(err) as _)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgrade_request(key: &str) -> http::request::Builder {
+        http::Request::get("/ws")
+            .header(header::CONNECTION, "keep-alive, Upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, key)
+    }
+
+    fn from_request(request: http::request::Builder) -> Result<Upgrade, InvalidUpgrade> {
+        let (mut parts, ()) = request.body(()).unwrap().into_parts();
+        Upgrade::from_request(&mut parts)
+    }
+
+    #[test]
+    fn accept_key() {
+        // the sample handshake of the RFC 6455
+        let upgrade = from_request(upgrade_request("dGhlIHNhbXBsZSBub25jZQ==")).unwrap();
+        assert_eq!(upgrade.accept, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn invalid_upgrade() {
+        let not_upgrade = http::Request::get("/ws").header(header::SEC_WEBSOCKET_VERSION, "13");
+        assert!(matches!(
+            from_request(not_upgrade),
+            Err(InvalidUpgrade::NotUpgrade)
+        ));
+
+        let mut old_version = upgrade_request("dGhlIHNhbXBsZSBub25jZQ==");
+        old_version
+            .headers_mut()
+            .unwrap()
+            .insert(header::SEC_WEBSOCKET_VERSION, HeaderValue::from_static("8"));
+        assert!(matches!(
+            from_request(old_version),
+            Err(InvalidUpgrade::UnsupportedVersion)
+        ));
+
+        // decoded to 15 bytes instead of 16
+        assert!(matches!(
+            from_request(upgrade_request("dGhlIHNhbXBsZSBub25jZQ")),
+            Err(InvalidUpgrade::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn invalid_upgrade_response() {
+        let resp = InvalidUpgrade::NotUpgrade.into_response().unwrap();
+        assert_eq!(resp.status(), StatusCode::UPGRADE_REQUIRED);
+        assert_eq!(resp.headers()[header::UPGRADE], "websocket");
+
+        let resp = InvalidUpgrade::InvalidKey.into_response().unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.headers()[header::SEC_WEBSOCKET_VERSION], "13");
+    }
+}
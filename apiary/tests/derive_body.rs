@@ -0,0 +1,51 @@
+use apiary::http_body::Full;
+use apiary::request::{self, Body};
+use bytes::Bytes;
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Deserialize, Body)]
+#[body(json)]
+pub struct NewPet {
+    pub name: String,
+    pub age: u32,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Body)]
+#[body(form)]
+pub struct Search {
+    pub q: String,
+}
+
+fn body(data: &'static str) -> request::BoxBody {
+    request::boxed(Full::new(Bytes::from(data)))
+}
+
+#[tokio::test]
+async fn json_body() {
+    let pet = NewPet::decode(body(r#"{"name":"tom","age":3}"#))
+        .await
+        .unwrap();
+    assert_eq!(
+        pet,
+        NewPet {
+            name: "tom".into(),
+            age: 3
+        }
+    );
+    assert!(NewPet::decode(body("name=tom")).await.is_err());
+
+    assert_eq!(NewPet::CONTENT_TYPE, "application/json");
+    assert!(NewPet::accepts("application/json; charset=utf-8"));
+    assert!(!NewPet::accepts("application/x-www-form-urlencoded"));
+}
+
+#[tokio::test]
+async fn form_body() {
+    let search = Search::decode(body("q=cute+cats")).await.unwrap();
+    assert_eq!(search.q, "cute cats");
+    assert!(Search::decode(body("p=1")).await.is_err());
+
+    assert_eq!(Search::CONTENT_TYPE, "application/x-www-form-urlencoded");
+    assert!(Search::accepts("application/x-www-form-urlencoded"));
+    assert!(!Search::accepts("application/json"));
+}
@@ -0,0 +1,132 @@
+use apiary::http::{header, request::Parts, Request, StatusCode};
+use apiary::http_body::Body as _;
+use apiary::response::{Body, Response};
+use apiary::{Negotiated, Response};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+    pub id: u32,
+}
+
+#[derive(Debug, thiserror::Error, Response)]
+pub enum PetError {
+    #[error("pet not found")]
+    #[status(404)]
+    NotFound,
+    #[error("pet conflicts")]
+    #[status(409, json)]
+    Conflict(Conflict),
+    #[error("invalid name {0:?}")]
+    #[status(400, text)]
+    InvalidName(String),
+    #[error("negotiated")]
+    #[status(422)]
+    Negotiated(Negotiated<Conflict>),
+    #[error("internal")]
+    Internal(String),
+}
+
+/// The status of the whole struct.
+#[derive(Debug, Response)]
+#[status(201)]
+pub struct Created(String);
+
+async fn read(resp: apiary::http::Response<Body>) -> (StatusCode, Option<String>, String) {
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_owned());
+
+    let mut body = resp.into_body();
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data.unwrap());
+    }
+    (status, content_type, String::from_utf8(buf).unwrap())
+}
+
+fn accept(accept: &str) -> Parts {
+    Request::get("/")
+        .header(header::ACCEPT, accept)
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0
+}
+
+#[tokio::test]
+async fn variant_status_and_encoding() {
+    let (status, _, body) = read(PetError::NotFound.into_response().unwrap()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, "");
+
+    let resp = PetError::Conflict(Conflict { id: 7 }).into_response();
+    let (status, content_type, body) = read(resp.unwrap()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(content_type.as_deref(), Some("application/json"));
+    assert_eq!(body, r#"{"id":7}"#);
+
+    let resp = PetError::InvalidName("x".into()).into_response();
+    let (status, content_type, body) = read(resp.unwrap()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(content_type.unwrap().starts_with("text/plain"));
+    assert_eq!(body, r#"invalid name "x""#);
+
+    let resp = Created("made".into()).into_response().unwrap();
+    assert_eq!(read(resp).await.0, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn result_error_without_status_is_server_error() {
+    let resp = Err::<String, _>(PetError::Internal("oops".into())).into_response();
+    let (status, _, body) = read(resp.unwrap()).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body, "oops");
+
+    let resp = Err::<String, _>(PetError::NotFound)
+        .into_response()
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = Ok::<_, PetError>(String::from("ok"))
+        .into_response()
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn status_overrides_except_not_acceptable() {
+    let negotiated = || PetError::Negotiated(Negotiated(Conflict { id: 1 }));
+
+    let resp = negotiated()
+        .into_response_for(&accept("application/json"))
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // the failed negotiation keeps its 406, as the body isn't the value
+    let resp = negotiated()
+        .into_response_for(&accept("text/html"))
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+}
+
+#[test]
+fn statuses() {
+    let statuses = PetError::statuses();
+    for status in [
+        StatusCode::NOT_FOUND,
+        StatusCode::CONFLICT,
+        StatusCode::BAD_REQUEST,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        StatusCode::NOT_ACCEPTABLE,
+    ] {
+        assert!(statuses.contains(&status), "{:?} in {:?}", status, statuses);
+    }
+    assert_eq!(Created::statuses(), [StatusCode::CREATED]);
+
+    let statuses = <Result<Created, PetError>>::statuses();
+    assert_eq!(statuses[0], StatusCode::CREATED);
+    assert!(statuses.contains(&StatusCode::NOT_FOUND));
+}
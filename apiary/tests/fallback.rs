@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use apiary::api;
+use apiary::http::{header, Request, StatusCode};
+use apiary::rejection::BoxRequest;
+use apiary::server::Server;
+use apiary::Negotiated;
+
+#[api(server(BooksServer))]
+#[async_trait::async_trait]
+pub trait Books {
+    #[get("/books")]
+    async fn list_books(self: Arc<Self>) -> String;
+
+    #[fallback]
+    async fn missing(self: Arc<Self>, request: BoxRequest) -> Negotiated<Vec<String>>;
+}
+
+struct Imp;
+
+#[async_trait::async_trait]
+impl Books for Imp {
+    async fn list_books(self: Arc<Self>) -> String {
+        "books".into()
+    }
+
+    async fn missing(self: Arc<Self>, request: BoxRequest) -> Negotiated<Vec<String>> {
+        Negotiated(vec![request.uri().path().to_owned()])
+    }
+}
+
+async fn get(uri: &str, accept: &str) -> apiary::http::Response<apiary::response::Body> {
+    let request = Request::get(uri)
+        .header(header::ACCEPT, accept)
+        .body(apiary::http_body::Empty::<bytes::Bytes>::new())
+        .unwrap();
+    BooksServer(Arc::new(Imp)).serve(request).await.unwrap()
+}
+
+#[tokio::test]
+async fn fallback_negotiates() {
+    let resp = get("/nope", "application/json").await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/json");
+
+    let resp = get("/nope", "text/html").await;
+    assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+
+    let resp = get("/books", "text/html").await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
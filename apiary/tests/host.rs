@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use apiary::api;
+use apiary::http::{header, Request, StatusCode};
+use apiary::http_body::Body as _;
+use apiary::server::Server;
+
+#[api(server(TenantServer), host = "{tenant}.shop.example.com")]
+#[async_trait::async_trait]
+pub trait Tenant {
+    #[get("/items/{id}")]
+    async fn get_item(self: Arc<Self>, tenant: String, id: u32) -> String;
+
+    #[get("/stats")]
+    async fn stats(self: Arc<Self>, tenant: u32) -> String;
+}
+
+struct Imp;
+
+#[async_trait::async_trait]
+impl Tenant for Imp {
+    async fn get_item(self: Arc<Self>, tenant: String, id: u32) -> String {
+        format!("{} {}", tenant, id)
+    }
+
+    async fn stats(self: Arc<Self>, tenant: u32) -> String {
+        format!("stats {}", tenant)
+    }
+}
+
+async fn get(host: &str, uri: &str) -> (StatusCode, String) {
+    let request = Request::get(uri)
+        .header(header::HOST, host)
+        .body(apiary::http_body::Empty::<bytes::Bytes>::new())
+        .unwrap();
+    let resp = TenantServer(Arc::new(Imp)).serve(request).await.unwrap();
+    let status = resp.status();
+
+    let mut body = resp.into_body();
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data.unwrap());
+    }
+    (status, String::from_utf8(buf).unwrap())
+}
+
+#[tokio::test]
+async fn host_params_are_bound() {
+    assert_eq!(
+        get("acme.shop.example.com", "/items/7").await,
+        (StatusCode::OK, "acme 7".into())
+    );
+    // the host is lowercased, without the port and the trailing dot
+    assert_eq!(
+        get("ACME.Shop.example.com.:8080", "/items/7").await,
+        (StatusCode::OK, "acme 7".into())
+    );
+    assert_eq!(
+        get("42.shop.example.com", "/stats").await,
+        (StatusCode::OK, "stats 42".into())
+    );
+}
+
+#[tokio::test]
+async fn other_hosts_are_rejected() {
+    for host in [
+        "shop.example.com",
+        ".shop.example.com",
+        "a.b.shop.example.com",
+        "acme.example.org",
+    ] {
+        assert_eq!(
+            get(host, "/items/7").await.0,
+            StatusCode::NOT_FOUND,
+            "{}",
+            host
+        );
+    }
+
+    let (status, body) = get("acme.shop.example.com", "/stats").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("tenant"), "{}", body);
+}
@@ -0,0 +1,174 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use apiary::http::{Request, StatusCode};
+use apiary::http_body::Body as _;
+use apiary::rejection::BoxRequest;
+use apiary::server::{Handler, ServeResult, Server};
+use apiary::{api, BoxError, MatchedRoute};
+use tower::limit::ConcurrencyLimitLayer;
+use tower::timeout::TimeoutLayer;
+
+static LAYERS_BUILT: AtomicUsize = AtomicUsize::new(0);
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static MAX_RUNNING: AtomicUsize = AtomicUsize::new(0);
+static NAMES_PARSED: AtomicUsize = AtomicUsize::new(0);
+static TWICE_CALLED: AtomicUsize = AtomicUsize::new(0);
+
+fn limit() -> ConcurrencyLimitLayer {
+    LAYERS_BUILT.fetch_add(1, Ordering::SeqCst);
+    ConcurrencyLimitLayer::new(1)
+}
+
+/// Path parameter which counts how many times it's parsed.
+#[derive(Debug, Clone)]
+pub struct Name(String);
+
+impl FromStr for Name {
+    type Err = BoxError;
+
+    fn from_str(s: &str) -> Result<Self, BoxError> {
+        NAMES_PARSED.fetch_add(1, Ordering::SeqCst);
+        Ok(Name(s.to_owned()))
+    }
+}
+
+/// Layer which calls the handler twice, like retrying it.
+#[derive(Clone)]
+struct Twice<S>(S);
+
+impl<S> tower::Layer<S> for Twice<()> {
+    type Service = Twice<S>;
+
+    fn layer(&self, inner: S) -> Twice<S> {
+        Twice(inner)
+    }
+}
+
+impl<S> tower::Service<BoxRequest> for Twice<S>
+where
+    S: tower::Service<
+            BoxRequest,
+            Response = apiary::http::Response<apiary::response::Body>,
+            Error = BoxError,
+            Future = ServeResult,
+        > + Clone
+        + Send
+        + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = ServeResult;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: BoxRequest) -> Self::Future {
+        let handler = request.extensions().get::<Handler>().cloned().unwrap();
+        let first = self.0.call(request);
+        let mut inner = self.0.clone();
+        Box::pin(async move {
+            first.await?;
+            let mut request = Request::new(apiary::request::BoxBody::default());
+            request.extensions_mut().insert(handler);
+            inner.call(request).await
+        })
+    }
+}
+
+#[api(server(AdminServer))]
+#[async_trait::async_trait]
+pub trait Admin {
+    #[get("/admin/{id}")]
+    #[layer(limit())]
+    async fn admin(self: Arc<Self>, id: u32) -> String;
+
+    #[get("/slow")]
+    #[layer(TimeoutLayer::new(Duration::from_millis(10)))]
+    async fn slow(self: Arc<Self>) -> String;
+
+    #[get("/twice/{name}")]
+    #[layer(Twice(()))]
+    async fn twice(self: Arc<Self>, name: Name) -> String;
+}
+
+struct Imp;
+
+#[async_trait::async_trait]
+impl Admin for Imp {
+    async fn admin(self: Arc<Self>, id: u32) -> String {
+        let running = RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+        MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+        format!("admin {}", id)
+    }
+
+    async fn slow(self: Arc<Self>) -> String {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        "slow".into()
+    }
+
+    async fn twice(self: Arc<Self>, name: Name) -> String {
+        TWICE_CALLED.fetch_add(1, Ordering::SeqCst);
+        name.0
+    }
+}
+
+async fn get(uri: &str) -> (StatusCode, Option<MatchedRoute>, String) {
+    let request = Request::get(uri)
+        .body(apiary::http_body::Empty::<bytes::Bytes>::new())
+        .unwrap();
+    let resp = AdminServer(Arc::new(Imp)).serve(request).await.unwrap();
+    let status = resp.status();
+    let matched = resp.extensions().get::<MatchedRoute>().copied();
+
+    let mut body = resp.into_body();
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data.unwrap());
+    }
+    (status, matched, String::from_utf8(buf).unwrap())
+}
+
+#[tokio::test]
+async fn layers_are_built_once() {
+    let calls: Vec<_> = (0..4)
+        .map(|id| tokio::spawn(async move { get(&format!("/admin/{}", id)).await }))
+        .collect();
+    for (id, call) in calls.into_iter().enumerate() {
+        let (status, matched, body) = call.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(matched.unwrap().template, "/admin/{id}");
+        assert_eq!(body, format!("admin {}", id));
+    }
+
+    assert_eq!(LAYERS_BUILT.load(Ordering::SeqCst), 1);
+    assert_eq!(MAX_RUNNING.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn layer_error_is_service_unavailable() {
+    let (status, matched, _) = get("/slow").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(matched.unwrap().handler, "slow");
+}
+
+#[tokio::test]
+async fn invalid_param_is_rejected_before_layers() {
+    let (status, _, _) = get("/admin/x").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn params_are_parsed_once() {
+    let (status, _, body) = get("/twice/tom").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "tom");
+    assert_eq!(TWICE_CALLED.load(Ordering::SeqCst), 2);
+    assert_eq!(NAMES_PARSED.load(Ordering::SeqCst), 1);
+}
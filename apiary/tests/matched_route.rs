@@ -0,0 +1,158 @@
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use apiary::http::request::Parts;
+use apiary::http::{Request, StatusCode};
+use apiary::http_body::Body as _;
+use apiary::rejection::BoxRequest;
+use apiary::request::{self, BoxBody, DecodeResult};
+use apiary::server::Server;
+use apiary::{api, MatchedRoute};
+
+static SEEN_BY_LAYER: Mutex<Option<MatchedRoute>> = Mutex::new(None);
+
+/// Body which takes the route from the request extensions, as the handler sees it.
+#[derive(Debug)]
+pub struct Seen(Option<MatchedRoute>);
+
+impl request::Body for Seen {
+    const CONTENT_TYPE: &'static str = "text/plain";
+
+    fn decode(_body: BoxBody) -> DecodeResult<Self> {
+        Box::pin(async { Ok(Seen(None)) })
+    }
+
+    fn decode_request(request: &Parts, _body: BoxBody) -> DecodeResult<Self> {
+        let matched = request.extensions.get::<MatchedRoute>().copied();
+        Box::pin(async move { Ok(Seen(matched)) })
+    }
+
+    fn accepts(content_type: &str) -> bool {
+        content_type == Self::CONTENT_TYPE
+    }
+}
+
+/// Layer which records the route from the request extensions.
+#[derive(Clone)]
+struct Record<S>(S);
+
+impl<S> apiary::tower::Layer<S> for Record<()> {
+    type Service = Record<S>;
+
+    fn layer(&self, inner: S) -> Record<S> {
+        Record(inner)
+    }
+}
+
+impl<S: apiary::tower::Service<BoxRequest>> apiary::tower::Service<BoxRequest> for Record<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: BoxRequest) -> S::Future {
+        *SEEN_BY_LAYER.lock().unwrap() = request.extensions().get::<MatchedRoute>().copied();
+        self.0.call(request)
+    }
+}
+
+#[api(server(NotesServer))]
+#[async_trait::async_trait]
+pub trait Notes {
+    #[put("/notes/{id}")]
+    async fn put_note(
+        self: Arc<Self>,
+        id: u32,
+        #[header("x-author")] author: String,
+        #[body] seen: Seen,
+    ) -> String;
+
+    #[get("/notes/{id}")]
+    #[layer(Record(()))]
+    async fn get_note(self: Arc<Self>, id: u32) -> String;
+}
+
+struct Imp;
+
+#[async_trait::async_trait]
+impl Notes for Imp {
+    async fn put_note(self: Arc<Self>, id: u32, author: String, seen: Seen) -> String {
+        let seen = seen.0.unwrap();
+        format!("{} {} by {}: {}", seen.template, seen.handler, author, id)
+    }
+
+    async fn get_note(self: Arc<Self>, id: u32) -> String {
+        format!("note {}", id)
+    }
+}
+
+async fn call(
+    request: apiary::http::request::Builder,
+) -> (StatusCode, Option<MatchedRoute>, String) {
+    let request = request
+        .body(apiary::http_body::Full::new(bytes::Bytes::from("body")))
+        .unwrap();
+    let resp = NotesServer(Arc::new(Imp)).serve(request).await.unwrap();
+    let status = resp.status();
+    let matched = resp.extensions().get::<MatchedRoute>().copied();
+
+    let mut body = resp.into_body();
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data.unwrap());
+    }
+    (status, matched, String::from_utf8(buf).unwrap())
+}
+
+const PUT_NOTE: MatchedRoute = MatchedRoute {
+    template: "/notes/{id}",
+    handler: "put_note",
+};
+
+#[tokio::test]
+async fn handler_and_response() {
+    let request = Request::put("/notes/1")
+        .header("x-author", "ann")
+        .header("content-type", "text/plain");
+    let (status, matched, body) = call(request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(matched, Some(PUT_NOTE));
+    assert_eq!(body, "/notes/{id} put_note by ann: 1");
+}
+
+#[tokio::test]
+async fn layers() {
+    let get_note = MatchedRoute {
+        template: "/notes/{id}",
+        handler: "get_note",
+    };
+
+    let (status, matched, _) = call(Request::get("/notes/2")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(matched, Some(get_note));
+    assert_eq!(*SEEN_BY_LAYER.lock().unwrap(), Some(get_note));
+}
+
+#[tokio::test]
+async fn rejections_after_match() {
+    // the missing header
+    let request = Request::put("/notes/1").header("content-type", "text/plain");
+    let (status, matched, _) = call(request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(matched, Some(PUT_NOTE));
+
+    let request = Request::put("/notes/1")
+        .header("x-author", "ann")
+        .header("content-type", "application/json");
+    let (status, matched, _) = call(request).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(matched, Some(PUT_NOTE));
+
+    // no route is matched
+    let (status, matched, _) = call(Request::get("/notes")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(matched, None);
+}
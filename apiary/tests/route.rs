@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use apiary::http::{Method, Request, StatusCode};
+use apiary::rejection::Rejection;
+use apiary::route::{ParamInfo, ParamSource};
+use apiary::{api, RouteInfo};
+
+#[api(server(StoreServer))]
+#[async_trait::async_trait]
+pub trait Store {
+    /// Lists every item.
+    #[get("/items")]
+    async fn list_items(self: Arc<Self>) -> String;
+
+    #[get("/items/{id}")]
+    async fn get_item(self: Arc<Self>, id: u32, #[header("x-lang")] lang: Option<String>)
+        -> String;
+
+    #[delete("/items/{id}")]
+    async fn delete_item(self: Arc<Self>, id: u32, #[body] reason: String) -> String;
+}
+
+struct Imp;
+
+#[async_trait::async_trait]
+impl Store for Imp {
+    async fn list_items(self: Arc<Self>) -> String {
+        unimplemented!()
+    }
+
+    async fn get_item(self: Arc<Self>, _id: u32, _lang: Option<String>) -> String {
+        unimplemented!()
+    }
+
+    async fn delete_item(self: Arc<Self>, _id: u32, _reason: String) -> String {
+        unimplemented!()
+    }
+}
+
+fn request(method: Method, uri: &str) -> Request<()> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-lang", "en")
+        .body(())
+        .unwrap()
+}
+
+#[test]
+fn recognizes_handlers() {
+    let route = StoreRoute::recognize(&request(Method::GET, "/items")).unwrap();
+    assert!(matches!(route, StoreRoute::ListItems));
+    assert_eq!(route.name(), "list_items");
+
+    let route = StoreRoute::recognize(&request(Method::GET, "/items/7")).unwrap();
+    assert!(matches!(
+        &route,
+        StoreRoute::GetItem { id: 7, lang: Some(lang) } if lang == "en"
+    ));
+    assert_eq!(route.name(), "get_item");
+
+    // the body isn't a field of the route
+    let route = StoreRoute::recognize(&request(Method::DELETE, "/items/7")).unwrap();
+    assert!(matches!(route, StoreRoute::DeleteItem { id: 7 }));
+    assert_eq!(route.name(), "delete_item");
+
+    let route = StoreRoute::recognize(&request(Method::HEAD, "/items/7")).unwrap();
+    assert_eq!(route.name(), "get_item");
+}
+
+#[test]
+fn rejects_unknown_requests() {
+    match StoreRoute::recognize(&request(Method::GET, "/users")) {
+        Err(rejection @ Rejection::NotFound(_)) => {
+            assert_eq!(rejection.status(), StatusCode::NOT_FOUND);
+            assert_eq!(rejection.request().uri(), "/users");
+        }
+        _ => panic!("expected NotFound"),
+    }
+
+    match StoreRoute::recognize(&request(Method::PUT, "/items/7")) {
+        Err(Rejection::MethodNotAllowed(rejection)) => {
+            assert_eq!(
+                rejection.allow,
+                [Method::GET, Method::HEAD, Method::DELETE, Method::OPTIONS]
+            );
+        }
+        _ => panic!("expected MethodNotAllowed"),
+    }
+
+    let rejection = StoreRoute::recognize(&request(Method::GET, "/items/seven"))
+        .err()
+        .unwrap();
+    assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn lists_routes() {
+    const ID: ParamInfo = ParamInfo {
+        name: "id",
+        source: ParamSource::Path,
+        ty: "u32",
+    };
+    const LANG: ParamInfo = ParamInfo {
+        name: "lang",
+        source: ParamSource::Header("x-lang"),
+        ty: "Option<String>",
+    };
+    const REASON: ParamInfo = ParamInfo {
+        name: "reason",
+        source: ParamSource::Body,
+        ty: "String",
+    };
+    let route = |method, path, handler, doc, params| RouteInfo {
+        method,
+        path,
+        handler,
+        websocket: false,
+        version: None,
+        params,
+        doc,
+    };
+
+    assert_eq!(
+        <Imp as Store>::routes(),
+        [
+            route(
+                Method::GET,
+                "/items",
+                "list_items",
+                "Lists every item.",
+                &[]
+            ),
+            route(Method::GET, "/items/{id}", "get_item", "", &[ID, LANG]),
+            route(
+                Method::DELETE,
+                "/items/{id}",
+                "delete_item",
+                "",
+                &[ID, REASON]
+            ),
+        ]
+    );
+}
@@ -0,0 +1,155 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use apiary::http::{self, header, Method, Request, StatusCode};
+use apiary::http_body::Body as _;
+use apiary::rejection::Rejection;
+use apiary::response::{Body, Response as _};
+use apiary::server::Server;
+use apiary::{api, BoxError, MatchedRoute};
+
+/// Pet kind, parsed from the path by its `FromStr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Cat,
+    Dog,
+}
+
+impl FromStr for Kind {
+    type Err = BoxError;
+
+    fn from_str(s: &str) -> Result<Self, BoxError> {
+        match s {
+            "cat" => Ok(Kind::Cat),
+            "dog" => Ok(Kind::Dog),
+            _ => Err(format!("unknown kind {}", s).into()),
+        }
+    }
+}
+
+#[api(server(PetsServer))]
+#[async_trait::async_trait]
+pub trait Pets {
+    #[get("/pets/{kind}/{name}")]
+    async fn get_pet(self: Arc<Self>, kind: Kind, name: String) -> String;
+
+    #[put("/pets/{kind}/{name}")]
+    async fn put_pet(
+        self: Arc<Self>,
+        kind: Kind,
+        name: String,
+        #[header("x-owner")] owner: String,
+        #[header("x-age")] age: Option<u32>,
+        #[body] body: String,
+    ) -> String;
+}
+
+/// Renders the rejections with a custom body.
+struct Custom;
+
+#[async_trait::async_trait]
+impl Pets for Custom {
+    async fn get_pet(self: Arc<Self>, kind: Kind, name: String) -> String {
+        format!("{:?} {}", kind, name)
+    }
+
+    async fn put_pet(
+        self: Arc<Self>,
+        kind: Kind,
+        name: String,
+        owner: String,
+        age: Option<u32>,
+        body: String,
+    ) -> String {
+        format!("{:?} {} of {} aged {:?}: {}", kind, name, owner, age, body)
+    }
+
+    fn render_rejection(&self, rejection: Rejection) -> Result<http::Response<Body>, BoxError> {
+        let status = rejection.status();
+        let route = rejection.request().extensions().get::<MatchedRoute>();
+        let body = format!("custom {} {:?}", status.as_u16(), route.map(|r| r.handler));
+
+        let mut resp = body.into_response()?;
+        *resp.status_mut() = status;
+        if let Rejection::MethodNotAllowed(rejection) = rejection {
+            let allow: Vec<_> = rejection.allow.iter().map(Method::as_str).collect();
+            resp.headers_mut()
+                .insert(header::ALLOW, allow.join(", ").parse()?);
+        }
+        Ok(resp)
+    }
+}
+
+async fn call(request: http::request::Builder) -> (StatusCode, http::HeaderMap, String) {
+    let request = request
+        .body(apiary::http_body::Full::new(bytes::Bytes::from("body")))
+        .unwrap();
+    let resp = PetsServer(Arc::new(Custom)).serve(request).await.unwrap();
+    let status = resp.status();
+    let headers = resp.headers().clone();
+
+    let mut body = resp.into_body();
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data.unwrap());
+    }
+    (status, headers, String::from_utf8(buf).unwrap())
+}
+
+#[tokio::test]
+async fn routes_by_template() {
+    let (status, _, body) = call(Request::get("/pets/cat/Tom%20%26%20Jerry")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "Cat Tom & Jerry");
+
+    let request = Request::put("/pets/dog/Rex")
+        .header("x-owner", "Ann")
+        .header("x-age", "3");
+    let (status, _, body) = call(request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "Dog Rex of Ann aged Some(3): body");
+
+    let (_, _, body) = call(Request::put("/pets/dog/Rex").header("x-owner", "Ann")).await;
+    assert_eq!(body, "Dog Rex of Ann aged None: body");
+}
+
+#[tokio::test]
+async fn rejections_are_rendered() {
+    let (status, _, body) = call(Request::get("/pets/bird/Tweety")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "custom 400 None");
+
+    // the missing header is rejected after the route is matched
+    let (status, _, body) = call(Request::put("/pets/dog/Rex")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "custom 400 Some(\"put_pet\")");
+
+    let request = Request::put("/pets/dog/Rex")
+        .header("x-owner", "Ann")
+        .header("x-age", "old");
+    let (status, _, _) = call(request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _, _) = call(Request::get("/pets/cat/..")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _, body) = call(Request::get("/pets/cat")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, "custom 404 None");
+
+    let (status, headers, _) = call(Request::delete("/pets/cat/Tom")).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(headers[header::ALLOW], "GET, HEAD, PUT, OPTIONS");
+}
+
+#[tokio::test]
+async fn head_and_options() {
+    let (status, headers, body) = call(Request::head("/pets/cat/Tom")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_LENGTH], "7");
+    assert_eq!(body, "");
+
+    let (status, headers, _) = call(Request::options("/pets/cat/Tom")).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(headers[header::ALLOW], "GET, HEAD, PUT, OPTIONS");
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use apiary::api;
+use apiary::http::{Request, StatusCode};
+use apiary::http_body::Body as _;
+use apiary::server::Server;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Search {
+    pub q: String,
+    pub page: Option<u32>,
+}
+
+#[api(server(DocsServer), urls(DocsUrls))]
+#[async_trait::async_trait]
+pub trait Docs {
+    #[get("/docs/{title}/")]
+    async fn get_doc(self: Arc<Self>, title: String) -> String;
+
+    #[get("/search")]
+    async fn search(self: Arc<Self>, #[query] search: Search) -> String;
+
+    #[get("/count")]
+    async fn count(self: Arc<Self>, #[query] n: u32) -> String;
+}
+
+struct Imp;
+
+#[async_trait::async_trait]
+impl Docs for Imp {
+    async fn get_doc(self: Arc<Self>, title: String) -> String {
+        title
+    }
+
+    async fn search(self: Arc<Self>, search: Search) -> String {
+        format!("{} {:?}", search.q, search.page)
+    }
+
+    async fn count(self: Arc<Self>, n: u32) -> String {
+        n.to_string()
+    }
+}
+
+async fn get(uri: apiary::http::Uri) -> (StatusCode, String) {
+    let request = Request::get(uri)
+        .body(apiary::http_body::Empty::<bytes::Bytes>::new())
+        .unwrap();
+    let resp = DocsServer(Arc::new(Imp)).serve(request).await.unwrap();
+    let status = resp.status();
+
+    let mut body = resp.into_body();
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data.unwrap());
+    }
+    (status, String::from_utf8(buf).unwrap())
+}
+
+#[tokio::test]
+async fn path_round_trips() {
+    let uri = DocsUrls::get_doc("a/b c?#%".into()).unwrap();
+    assert_eq!(uri, "/docs/a%2Fb%20c%3F%23%25/");
+    assert_eq!(get(uri).await, (StatusCode::OK, "a/b c?#%".into()));
+}
+
+#[tokio::test]
+async fn query_round_trips() {
+    let uri = DocsUrls::search(Search {
+        q: "fish & chips".into(),
+        page: Some(2),
+    })
+    .unwrap();
+    assert_eq!(
+        get(uri).await,
+        (StatusCode::OK, "fish & chips Some(2)".into())
+    );
+
+    let uri = DocsUrls::search(Search {
+        q: "".into(),
+        page: None,
+    })
+    .unwrap();
+    assert_eq!(get(uri).await, (StatusCode::OK, " None".into()));
+}
+
+#[test]
+fn dot_segments_are_rejected() {
+    for title in ["", ".", ".."] {
+        assert!(DocsUrls::get_doc(title.into()).is_err(), "{:?}", title);
+    }
+}
+
+#[test]
+fn query_must_be_struct() {
+    assert!(DocsUrls::count(3).is_err());
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use apiary::api;
+use apiary::http::{header, Request, StatusCode};
+use apiary::http_body::Body as _;
+use apiary::response;
+use apiary::server::Server;
+
+#[api(server(ByPathServer, versioning = "path"))]
+#[async_trait::async_trait]
+pub trait ByPath {
+    #[get("/pets", version = "1")]
+    async fn pets_v1(self: Arc<Self>) -> String;
+
+    #[get("/pets", version = "2")]
+    async fn pets_v2(self: Arc<Self>) -> String;
+
+    #[get("/health")]
+    async fn health(self: Arc<Self>) -> String;
+}
+
+#[api(server(ByHeaderServer, versioning = "header", default_version = "1"))]
+#[async_trait::async_trait]
+pub trait ByHeader {
+    #[get("/pets", version = "1")]
+    async fn pets_v1(self: Arc<Self>) -> String;
+
+    #[get("/pets", version = "2")]
+    async fn pets_v2(self: Arc<Self>) -> String;
+}
+
+#[api(server(ByMediaTypeServer, versioning = "media_type", vendor = "acme"))]
+#[async_trait::async_trait]
+pub trait ByMediaType {
+    #[get("/pets", version = "1")]
+    async fn pets_v1(self: Arc<Self>) -> String;
+
+    #[get("/pets", version = "2")]
+    async fn pets_v2(self: Arc<Self>) -> String;
+}
+
+struct Imp;
+
+#[async_trait::async_trait]
+impl ByPath for Imp {
+    async fn pets_v1(self: Arc<Self>) -> String {
+        "v1".into()
+    }
+
+    async fn pets_v2(self: Arc<Self>) -> String {
+        "v2".into()
+    }
+
+    async fn health(self: Arc<Self>) -> String {
+        "ok".into()
+    }
+}
+
+#[async_trait::async_trait]
+impl ByHeader for Imp {
+    async fn pets_v1(self: Arc<Self>) -> String {
+        "v1".into()
+    }
+
+    async fn pets_v2(self: Arc<Self>) -> String {
+        "v2".into()
+    }
+}
+
+#[async_trait::async_trait]
+impl ByMediaType for Imp {
+    async fn pets_v1(self: Arc<Self>) -> String {
+        "v1".into()
+    }
+
+    async fn pets_v2(self: Arc<Self>) -> String {
+        "v2".into()
+    }
+}
+
+async fn call<S: Server>(
+    server: S,
+    request: apiary::http::request::Builder,
+) -> (StatusCode, String) {
+    let request = request
+        .body(apiary::http_body::Empty::<bytes::Bytes>::new())
+        .unwrap();
+    let resp: apiary::http::Response<response::Body> = server.serve(request).await.unwrap();
+    let status = resp.status();
+
+    let mut body = resp.into_body();
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        buf.extend_from_slice(&data.unwrap());
+    }
+    (status, String::from_utf8(buf).unwrap())
+}
+
+#[tokio::test]
+async fn path_versioning() {
+    let get = |uri| call(ByPathServer(Arc::new(Imp)), Request::get(uri));
+
+    assert_eq!(get("/v1/pets").await, (StatusCode::OK, "v1".into()));
+    assert_eq!(get("/v2/pets").await, (StatusCode::OK, "v2".into()));
+    // the unversioned handler takes any version
+    assert_eq!(get("/health").await, (StatusCode::OK, "ok".into()));
+    assert_eq!(get("/v2/health").await, (StatusCode::OK, "ok".into()));
+
+    // the unknown version in the path is just an unknown path
+    assert_eq!(get("/v3/pets").await.0, StatusCode::NOT_FOUND);
+    assert_eq!(get("/pets").await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn header_versioning() {
+    let get = |version: Option<&'static str>| {
+        let mut request = Request::get("/pets");
+        if let Some(version) = version {
+            request = request.header("accept-version", version);
+        }
+        call(ByHeaderServer(Arc::new(Imp)), request)
+    };
+
+    assert_eq!(get(Some("2")).await, (StatusCode::OK, "v2".into()));
+    // the default version is used without the header
+    assert_eq!(get(None).await, (StatusCode::OK, "v1".into()));
+
+    let (status, body) = get(Some("3")).await;
+    assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+    assert!(body.contains('3'), "{}", body);
+}
+
+#[tokio::test]
+async fn media_type_versioning() {
+    let get = |accept: &'static str| {
+        let request = Request::get("/pets").header(header::ACCEPT, accept);
+        call(ByMediaTypeServer(Arc::new(Imp)), request)
+    };
+
+    assert_eq!(
+        get("application/vnd.acme.v2+json").await,
+        (StatusCode::OK, "v2".into())
+    );
+    assert_eq!(
+        get("application/vnd.acme.v3+json").await.0,
+        StatusCode::NOT_ACCEPTABLE
+    );
+    // no default version, so the versioned handlers don't match
+    assert_eq!(get("application/json").await.0, StatusCode::NOT_FOUND);
+}